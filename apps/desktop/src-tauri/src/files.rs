@@ -0,0 +1,1078 @@
+use crate::scan::epoch_millis;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager};
+
+/// Largest file `read_file_text` will load, so opening a log or dump by
+/// accident doesn't freeze the editor.
+const MAX_TEXT_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// How much of a file `is_binary_file` looks at.
+const BINARY_SNIFF_BYTES: u64 = 8192;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    /// Whichever ending most lines use; `Lf` for files without line breaks.
+    fn detect(text: &str) -> Self {
+        let crlf = text.matches("\r\n").count();
+        let lf = text.matches('\n').count() - crlf;
+        if crlf > lf {
+            LineEnding::Crlf
+        } else {
+            LineEnding::Lf
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct FileText {
+    pub contents: String,
+    pub line_ending: LineEnding,
+}
+
+#[tauri::command]
+pub fn read_file_text(path: String) -> Result<FileText, String> {
+    let metadata = fs::metadata(&path).map_err(|e| format!("{path}: {e}"))?;
+    if !metadata.is_file() {
+        return Err(format!("{path} is not a file"));
+    }
+    if metadata.len() > MAX_TEXT_FILE_BYTES {
+        return Err(format!(
+            "{path} is too large to open ({} bytes, limit {MAX_TEXT_FILE_BYTES})",
+            metadata.len()
+        ));
+    }
+    let bytes = fs::read(&path).map_err(|e| format!("{path}: {e}"))?;
+    let contents =
+        String::from_utf8(bytes).map_err(|_| format!("{path} is not valid UTF-8 text"))?;
+    Ok(FileText {
+        line_ending: LineEnding::detect(&contents),
+        contents,
+    })
+}
+
+/// Guesses whether a file is binary from its first few KB: it is if they
+/// hold a NUL byte or more than a tenth of them aren't valid UTF-8. Empty
+/// files count as text.
+#[tauri::command]
+pub fn is_binary_file(path: String) -> Result<bool, String> {
+    looks_binary(Path::new(&path)).map_err(|e| format!("{path}: {e}"))
+}
+
+pub(crate) fn looks_binary(path: &Path) -> io::Result<bool> {
+    let mut head = Vec::new();
+    fs::File::open(path)?
+        .take(BINARY_SNIFF_BYTES)
+        .read_to_end(&mut head)?;
+    if head.contains(&0) {
+        return Ok(true);
+    }
+    let invalid: usize = head.utf8_chunks().map(|chunk| chunk.invalid().len()).sum();
+    Ok(invalid * 10 > head.len())
+}
+
+/// Writes `contents` to `path` with `write_atomic`. With `line_ending`,
+/// line breaks are converted to that style first.
+#[tauri::command]
+pub fn write_file_text(
+    path: String,
+    contents: String,
+    line_ending: Option<LineEnding>,
+) -> Result<(), String> {
+    let target = Path::new(&path);
+    let contents = match line_ending {
+        Some(LineEnding::Lf) => contents.replace("\r\n", "\n"),
+        Some(LineEnding::Crlf) => contents.replace("\r\n", "\n").replace('\n', "\r\n"),
+        None => contents,
+    };
+    write_atomic(target, contents.as_bytes()).map_err(|e| format!("{path}: {e}"))
+}
+
+/// Writes `bytes` to `path` through a temporary file in the same directory
+/// that is synced and then renamed over it, so a crash mid-write leaves
+/// either the old file or the new one, never half of each. An existing
+/// file keeps its permissions.
+pub(crate) fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Err(io::Error::new(ErrorKind::InvalidInput, "not a file path"));
+    };
+    let temp = dir.join(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        uuid::Uuid::new_v4().simple()
+    ));
+
+    let write = || -> io::Result<()> {
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(bytes)?;
+        if let Ok(existing) = fs::metadata(path) {
+            file.set_permissions(existing.permissions())?;
+        }
+        file.sync_all()?;
+        fs::rename(&temp, path)
+    };
+    write().inspect_err(|_| {
+        let _ = fs::remove_file(&temp);
+    })
+}
+
+#[tauri::command]
+pub fn create_file(path: String) -> Result<(), String> {
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map(|_| ())
+        .map_err(|e| format!("{path}: {e}"))
+}
+
+#[tauri::command]
+pub fn create_dir(path: String) -> Result<(), String> {
+    fs::create_dir(&path).map_err(|e| format!("{path}: {e}"))
+}
+
+/// Renames `from` to `to`. An existing `to` is only replaced with
+/// `overwrite`, except when it's `from` itself, as in a case-only rename on a
+/// case-insensitive filesystem.
+#[tauri::command]
+pub fn rename_entry(from: String, to: String, overwrite: Option<bool>) -> Result<(), String> {
+    let target = Path::new(&to);
+    if target.symlink_metadata().is_ok() && !overwrite.unwrap_or(false) && !same_entry(&from, &to) {
+        return Err(format!("{to} already exists"));
+    }
+    fs::rename(&from, target).map_err(|e| format!("{from}: {e}"))
+}
+
+fn same_entry(a: &str, b: &str) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Progress of a `move_entry` that has to copy, sent after each file.
+#[derive(Clone, Serialize)]
+pub struct MoveProgress {
+    pub copied_bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// Bytes in the regular files under `path`, without following symlinks.
+pub(crate) fn tree_size(path: &Path) -> io::Result<u64> {
+    let metadata = fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(if metadata.is_file() {
+            metadata.len()
+        } else {
+            0
+        });
+    }
+    let mut total = 0;
+    for entry in fs::read_dir(path)? {
+        total += tree_size(&entry?.path())?;
+    }
+    Ok(total)
+}
+
+/// Gives `to` the modification and access times of `metadata`. Best effort:
+/// not every platform lets a directory be opened for this.
+fn copy_times(metadata: &fs::Metadata, to: &Path) {
+    let (Ok(modified), Ok(accessed)) = (metadata.modified(), metadata.accessed()) else {
+        return;
+    };
+    let times = fs::FileTimes::new()
+        .set_modified(modified)
+        .set_accessed(accessed);
+    let is_dir = metadata.is_dir();
+    if let Ok(file) = fs::File::options().write(!is_dir).read(is_dir).open(to) {
+        let _ = file.set_times(times);
+    }
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(from)?, to)
+}
+
+#[cfg(windows)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    let target = fs::read_link(from)?;
+    if fs::metadata(from).is_ok_and(|m| m.is_dir()) {
+        std::os::windows::fs::symlink_dir(target, to)
+    } else {
+        std::os::windows::fs::symlink_file(target, to)
+    }
+}
+
+/// Copies `from` to `to` recursively, keeping permissions and times and
+/// recreating symlinks as links. `progress` gets each file copied and its
+/// size.
+fn copy_tree(from: &Path, to: &Path, progress: &mut dyn FnMut(&Path, u64)) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(from)?;
+    if metadata.file_type().is_symlink() {
+        return copy_symlink(from, to);
+    }
+    if metadata.is_dir() {
+        fs::create_dir(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_tree(&entry.path(), &to.join(entry.file_name()), progress)?;
+        }
+        // Last, so a read-only directory can still be filled
+        fs::set_permissions(to, metadata.permissions())?;
+    } else {
+        // `fs::copy` carries the permissions over itself
+        progress(from, fs::copy(from, to)?);
+    }
+    copy_times(&metadata, to);
+    Ok(())
+}
+
+fn remove_tree(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// Copies `source` under a temporary name next to `target` and renames it
+/// into place once complete, so a failure never leaves a partial `target`.
+/// With `replace`, an existing `target` is removed just before that rename.
+fn copy_into_place(
+    source: &Path,
+    target: &Path,
+    replace: bool,
+    progress: &mut dyn FnMut(&Path, u64),
+) -> io::Result<()> {
+    let (Some(dir), Some(name)) = (target.parent(), target.file_name()) else {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            "target has no file name",
+        ));
+    };
+    let staging = dir.join(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        uuid::Uuid::new_v4().simple()
+    ));
+    let copied = copy_tree(source, &staging, progress).and_then(|_| {
+        if replace && fs::symlink_metadata(target).is_ok() {
+            remove_tree(target)?;
+        }
+        fs::rename(&staging, target)
+    });
+    if copied.is_err() {
+        let _ = remove_tree(&staging);
+    }
+    copied
+}
+
+/// Renames `source` to `target`, or where they're on different filesystems
+/// falls back to `move_by_copy`.
+fn move_path(
+    source: &Path,
+    target: &Path,
+    progress: Option<&Channel<MoveProgress>>,
+) -> io::Result<()> {
+    match fs::rename(source, target) {
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => {}
+        result => return result,
+    }
+    move_by_copy(source, target, &mut |update| {
+        if let Some(channel) = progress {
+            let _ = channel.send(update);
+        }
+    })
+}
+
+/// Copies `source` to `target` and then deletes it, calling `progress`
+/// after each file. The source is only deleted once the copy is in place,
+/// so a failure leaves it untouched.
+fn move_by_copy(
+    source: &Path,
+    target: &Path,
+    progress: &mut dyn FnMut(MoveProgress),
+) -> io::Result<()> {
+    let total_bytes = tree_size(source)?;
+    let mut copied_bytes = 0;
+    let mut report = |_: &Path, bytes: u64| {
+        copied_bytes += bytes;
+        progress(MoveProgress {
+            copied_bytes,
+            total_bytes,
+        });
+    };
+    copy_into_place(source, target, false, &mut report)?;
+    remove_tree(source)
+}
+
+/// Moves `from` into the directory `to_dir`, keeping its name. Never
+/// replaces an entry already there. Moving to another filesystem falls back
+/// to copying; `move_entry_with_progress` reports on that copy.
+#[tauri::command]
+pub fn move_entry(from: String, to_dir: String) -> Result<(), String> {
+    move_into(&from, &to_dir, None)
+}
+
+/// Like `move_entry`, but sends `MoveProgress` over `channel` after each
+/// file when the move has to copy, e.g. for a large folder going to another
+/// drive. A move within one filesystem sends nothing.
+#[tauri::command]
+pub fn move_entry_with_progress(
+    from: String,
+    to_dir: String,
+    channel: Channel<MoveProgress>,
+) -> Result<(), String> {
+    move_into(&from, &to_dir, Some(&channel))
+}
+
+fn move_into(
+    from: &str,
+    to_dir: &str,
+    progress: Option<&Channel<MoveProgress>>,
+) -> Result<(), String> {
+    let source = Path::new(from);
+    let dir = Path::new(to_dir);
+    if !dir.is_dir() {
+        return Err(format!("{to_dir} is not a directory"));
+    }
+    let Some(name) = source.file_name() else {
+        return Err(format!("{from} has no file name"));
+    };
+    if let (Ok(source), Ok(dir)) = (fs::canonicalize(source), fs::canonicalize(dir)) {
+        if dir.starts_with(&source) {
+            return Err(format!("can't move {from} into itself"));
+        }
+    }
+    let target = dir.join(name);
+    if target.symlink_metadata().is_ok() {
+        return Err(format!("{} already exists", target.display()));
+    }
+    move_path(source, &target, progress).map_err(|e| format!("{from}: {e}"))
+}
+
+/// Progress of a `copy_entry_with_progress`, sent after each file.
+#[derive(Clone, Serialize)]
+pub struct CopyProgress {
+    pub copied_bytes: u64,
+    pub total_bytes: u64,
+    /// The file just copied.
+    pub current_file: String,
+}
+
+/// Copies the file or directory `from` to `to`, keeping modification times
+/// and permissions. An existing `to` is an error unless `overwrite`, in
+/// which case it's replaced once the copy is complete. A directory can't be
+/// copied into itself, nor over a directory containing it.
+#[tauri::command]
+pub fn copy_entry(from: String, to: String, overwrite: bool) -> Result<(), String> {
+    copy_to(&from, &to, overwrite, None)
+}
+
+/// Like `copy_entry`, but sends `CopyProgress` over `channel` after each
+/// file.
+#[tauri::command]
+pub fn copy_entry_with_progress(
+    from: String,
+    to: String,
+    overwrite: bool,
+    channel: Channel<CopyProgress>,
+) -> Result<(), String> {
+    copy_to(&from, &to, overwrite, Some(&channel))
+}
+
+fn copy_to(
+    from: &str,
+    to: &str,
+    overwrite: bool,
+    progress: Option<&Channel<CopyProgress>>,
+) -> Result<(), String> {
+    let source = fs::canonicalize(from).map_err(|e| format!("{from}: {e}"))?;
+    let target = Path::new(to);
+    let (Some(parent), Some(name)) = (target.parent(), target.file_name()) else {
+        return Err(format!("{to} is not a valid destination"));
+    };
+    let parent = if parent.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        parent
+    };
+    // The parent must exist; `to` itself may not yet
+    let resolved = fs::canonicalize(parent)
+        .map_err(|e| format!("{}: {e}", parent.display()))?
+        .join(name);
+    if resolved.starts_with(&source) {
+        return Err(format!("can't copy {from} into itself"));
+    }
+    if target.symlink_metadata().is_ok() {
+        if !overwrite {
+            return Err(format!("{to} already exists"));
+        }
+        if source.starts_with(&resolved) {
+            return Err(format!("can't replace {to}, which contains {from}"));
+        }
+    }
+
+    let total_bytes = tree_size(&source).map_err(|e| format!("{from}: {e}"))?;
+    let mut copied_bytes = 0;
+    let mut report = |file: &Path, bytes: u64| {
+        copied_bytes += bytes;
+        if let Some(channel) = progress {
+            let _ = channel.send(CopyProgress {
+                copied_bytes,
+                total_bytes,
+                current_file: file.to_string_lossy().to_string(),
+            });
+        }
+    };
+    copy_into_place(Path::new(from), target, overwrite, &mut report)
+        .map_err(|e| format!("{from}: {e}"))
+}
+
+/// How `delete_entry` removed a path.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Deletion {
+    Trashed,
+    Deleted,
+}
+
+/// Moves `path` to the OS trash, or with `permanent` deletes it outright.
+/// Where there is no trash (e.g. some network or removable drives) this
+/// fails rather than deleting anyway, so the caller can ask first and retry
+/// with `permanent`. A non-empty directory is only removed with `recursive`.
+#[tauri::command]
+pub fn delete_entry(path: String, recursive: bool, permanent: bool) -> Result<Deletion, String> {
+    let metadata = fs::symlink_metadata(&path).map_err(|e| format!("{path}: {e}"))?;
+    if metadata.is_dir() && !recursive {
+        let mut entries = fs::read_dir(&path).map_err(|e| format!("{path}: {e}"))?;
+        if entries.next().is_some() {
+            return Err(format!("{path} is not empty"));
+        }
+    }
+    if !permanent {
+        return trash::delete(&path)
+            .map(|_| Deletion::Trashed)
+            .map_err(|e| format!("{path}: couldn't move to the trash: {e}"));
+    }
+    remove_tree(Path::new(&path))
+        .map(|_| Deletion::Deleted)
+        .map_err(|e| format!("{path}: {e}"))
+}
+
+/// What `file_metadata` knows about a path. For a symbolic link
+/// `is_dir`, `is_file`, `size` and the times describe its target, unless the
+/// link is broken.
+#[derive(Serialize)]
+pub struct FileMeta {
+    pub is_dir: bool,
+    pub is_file: bool,
+    pub is_symlink: bool,
+    pub size: u64,
+    /// Unix epoch millis, where the platform records it.
+    pub modified: Option<u64>,
+    pub created: Option<u64>,
+    pub readonly: bool,
+    /// A file with any execute bit set. Always false on Windows, which has
+    /// no such bit.
+    pub executable: bool,
+    /// Where a symbolic link points, as stored in the link.
+    pub symlink_target: Option<String>,
+}
+
+/// Metadata for a single path without scanning anything. A missing path and
+/// one we may not look at get distinct messages.
+#[tauri::command]
+pub fn file_metadata(path: String) -> Result<FileMeta, String> {
+    let describe = |e: std::io::Error| match e.kind() {
+        ErrorKind::NotFound => format!("{path} does not exist"),
+        ErrorKind::PermissionDenied => format!("Permission denied: {path}"),
+        _ => format!("{path}: {e}"),
+    };
+    let link = fs::symlink_metadata(&path).map_err(describe)?;
+    let is_symlink = link.file_type().is_symlink();
+    let (metadata, symlink_target) = if is_symlink {
+        let target = fs::read_link(&path).map_err(describe)?;
+        // A broken link still describes itself
+        let metadata = fs::metadata(&path).unwrap_or(link);
+        (metadata, Some(target.to_string_lossy().to_string()))
+    } else {
+        (link, None)
+    };
+
+    Ok(FileMeta {
+        is_dir: metadata.is_dir(),
+        is_file: metadata.is_file(),
+        is_symlink,
+        size: if metadata.is_dir() { 0 } else { metadata.len() },
+        modified: metadata.modified().ok().and_then(epoch_millis),
+        created: metadata.created().ok().and_then(epoch_millis),
+        readonly: metadata.permissions().readonly(),
+        executable: metadata.is_file() && is_executable(&metadata),
+        symlink_target,
+    })
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// Sets or clears the owner, group and other execute bits of a file,
+/// leaving the rest of its mode alone.
+#[cfg(unix)]
+#[tauri::command]
+pub fn set_executable(path: String, executable: bool) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    let metadata = fs::metadata(&path).map_err(|e| format!("{path}: {e}"))?;
+    if !metadata.is_file() {
+        return Err(format!("{path} is not a file"));
+    }
+    let mut permissions = metadata.permissions();
+    let mode = permissions.mode();
+    permissions.set_mode(if executable {
+        mode | 0o111
+    } else {
+        mode & !0o111
+    });
+    fs::set_permissions(&path, permissions).map_err(|e| format!("{path}: {e}"))
+}
+
+#[cfg(not(unix))]
+#[tauri::command]
+pub fn set_executable(path: String, executable: bool) -> Result<(), String> {
+    let _ = (path, executable);
+    Err("Execute permissions are unsupported on this platform".to_string())
+}
+
+/// Turns a path from the frontend into an absolute one: a leading `~` is the
+/// home directory, and a relative path is taken from `base`, or the working
+/// directory without one. Existing paths are canonicalized, so symlinks are
+/// resolved; others only have `.` and `..` collapsed.
+#[tauri::command]
+pub fn resolve_path(app: AppHandle, path: String, base: Option<String>) -> Result<String, String> {
+    let home = app.path().home_dir().ok();
+    resolve(&path, base.as_deref(), home).map(|p| p.to_string_lossy().to_string())
+}
+
+fn resolve(path: &str, base: Option<&str>, home: Option<PathBuf>) -> Result<PathBuf, String> {
+    let mut components = Path::new(path).components();
+    let expanded = match components.next() {
+        Some(Component::Normal(first)) if first == "~" => {
+            let home = home.ok_or_else(|| format!("{path}: no home directory"))?;
+            home.join(components.as_path())
+        }
+        _ => PathBuf::from(path),
+    };
+    let absolute = if expanded.is_absolute() {
+        expanded
+    } else {
+        match base {
+            Some(base) => Path::new(base).join(expanded),
+            None => std::env::current_dir()
+                .map_err(|e| format!("{path}: {e}"))?
+                .join(expanded),
+        }
+    };
+    if let Ok(canonical) = fs::canonicalize(&absolute) {
+        return Ok(canonical);
+    }
+    let mut normalized = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            // `pop` stops at the root, as `/..` is `/`
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    Ok(normalized)
+}
+
+/// `path` with each component spelled the way it is on disk, for
+/// case-insensitive filesystems where `/Users/me/PROJECT` also opens
+/// `/Users/me/project`. Symlinks are left in place. The path must exist.
+#[tauri::command]
+pub fn canonical_path(path: String) -> Result<String, String> {
+    real_case(Path::new(&path))
+        .map(|p| p.to_string_lossy().to_string())
+        .map_err(|e| format!("{path}: {e}"))
+}
+
+pub(crate) fn real_case(path: &Path) -> io::Result<PathBuf> {
+    // On a case-sensitive filesystem this means every component already
+    // matches exactly
+    fs::symlink_metadata(path)?;
+    let mut real = PathBuf::new();
+    for component in path.components() {
+        let Component::Normal(name) = component else {
+            real.push(component);
+            continue;
+        };
+        let dir = if real.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            real.as_path()
+        };
+        let wanted = name.to_string_lossy().to_lowercase();
+        let mut folded = None;
+        // An unreadable directory lists nothing, so the name stays as given
+        for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+            let entry_name = entry.file_name();
+            if entry_name == name {
+                folded = Some(entry_name);
+                break;
+            }
+            if folded.is_none() && entry_name.to_string_lossy().to_lowercase() == wanted {
+                folded = Some(entry_name);
+            }
+        }
+        // Not listed, e.g. in a directory we can't read; keep it
+        real.push(folded.as_deref().unwrap_or(name));
+    }
+    Ok(real)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn path_in(dir: &TempDir, name: &str) -> String {
+        dir.path().join(name).to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn write_then_read_round_trips_text_and_line_endings() {
+        let dir = TempDir::new().unwrap();
+        let path = path_in(&dir, "notes.txt");
+        write_file_text(path.clone(), "one\ntwo\n".into(), Some(LineEnding::Crlf)).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"one\r\ntwo\r\n");
+
+        let text = read_file_text(path.clone()).unwrap();
+        assert_eq!(text.contents, "one\r\ntwo\r\n");
+        assert!(text.line_ending == LineEnding::Crlf);
+
+        write_file_text(path.clone(), text.contents, Some(LineEnding::Lf)).unwrap();
+        let text = read_file_text(path).unwrap();
+        assert_eq!(text.contents, "one\ntwo\n");
+        assert!(text.line_ending == LineEnding::Lf);
+        // Nothing is left behind from the temp-file write
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn read_file_text_refuses_files_over_the_limit() {
+        let dir = TempDir::new().unwrap();
+        let path = path_in(&dir, "big.log");
+        let file = fs::File::create(&path).unwrap();
+        file.set_len(MAX_TEXT_FILE_BYTES).unwrap();
+        assert!(read_file_text(path.clone()).is_ok());
+
+        file.set_len(MAX_TEXT_FILE_BYTES + 1).unwrap();
+        let err = read_file_text(path).err().unwrap();
+        assert!(err.contains("too large"), "{err}");
+    }
+
+    #[test]
+    fn create_file_and_dir_refuse_existing_entries() {
+        let dir = TempDir::new().unwrap();
+        let file = path_in(&dir, "new.txt");
+        let sub = path_in(&dir, "sub");
+        create_file(file.clone()).unwrap();
+        create_dir(sub.clone()).unwrap();
+        assert!(Path::new(&file).is_file());
+        assert!(Path::new(&sub).is_dir());
+
+        fs::write(&file, "kept").unwrap();
+        assert!(create_file(file.clone()).is_err());
+        assert!(create_dir(sub).is_err());
+        assert_eq!(fs::read_to_string(file).unwrap(), "kept");
+    }
+
+    #[test]
+    fn rename_entry_only_replaces_with_overwrite() {
+        let dir = TempDir::new().unwrap();
+        let (from, to) = (path_in(&dir, "from.txt"), path_in(&dir, "to.txt"));
+        fs::write(&from, "from").unwrap();
+        fs::write(&to, "to").unwrap();
+
+        let err = rename_entry(from.clone(), to.clone(), None).unwrap_err();
+        assert!(err.contains("already exists"), "{err}");
+        assert_eq!(fs::read_to_string(&to).unwrap(), "to");
+
+        rename_entry(from.clone(), to.clone(), Some(true)).unwrap();
+        assert!(!Path::new(&from).exists());
+        assert_eq!(fs::read_to_string(&to).unwrap(), "from");
+
+        let renamed = path_in(&dir, "renamed.txt");
+        rename_entry(to, renamed.clone(), None).unwrap();
+        assert_eq!(fs::read_to_string(renamed).unwrap(), "from");
+    }
+
+    #[test]
+    fn move_entry_keeps_the_name_and_never_replaces() {
+        let dir = TempDir::new().unwrap();
+        let (file, dest) = (path_in(&dir, "a.txt"), path_in(&dir, "dest"));
+        fs::write(&file, "a").unwrap();
+        fs::create_dir(&dest).unwrap();
+
+        move_entry(file.clone(), dest.clone()).unwrap();
+        assert!(!Path::new(&file).exists());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("dest/a.txt")).unwrap(),
+            "a"
+        );
+
+        fs::write(&file, "again").unwrap();
+        assert!(move_entry(file.clone(), dest.clone()).is_err());
+        assert!(move_entry(dest.clone(), dest).is_err());
+        assert_eq!(fs::read_to_string(file).unwrap(), "again");
+    }
+
+    #[test]
+    fn delete_entry_needs_recursive_for_a_non_empty_dir() {
+        let dir = TempDir::new().unwrap();
+        let sub = path_in(&dir, "sub");
+        fs::create_dir(&sub).unwrap();
+        fs::write(dir.path().join("sub/a.txt"), "a").unwrap();
+
+        let err = delete_entry(sub.clone(), false, true).unwrap_err();
+        assert!(err.contains("not empty"), "{err}");
+        assert_eq!(delete_entry(sub.clone(), true, true), Ok(Deletion::Deleted));
+        assert!(!Path::new(&sub).exists());
+    }
+
+    #[test]
+    fn delete_entry_never_falls_back_to_deleting_permanently() {
+        let dir = TempDir::new().unwrap();
+        let file = path_in(&dir, "a.txt");
+        fs::write(&file, "a").unwrap();
+        // Whether a trash is available here depends on the machine; either
+        // way the file must only be gone when it went to the trash
+        match delete_entry(file.clone(), false, false) {
+            Ok(deletion) => {
+                assert_eq!(deletion, Deletion::Trashed);
+                assert!(!Path::new(&file).exists());
+            }
+            Err(err) => {
+                assert!(err.contains("trash"), "{err}");
+                assert!(Path::new(&file).exists());
+            }
+        }
+    }
+
+    #[test]
+    fn file_metadata_describes_a_file_and_a_directory() {
+        let dir = TempDir::new().unwrap();
+        let file = path_in(&dir, "a.txt");
+        fs::write(&file, "hello").unwrap();
+
+        let meta = file_metadata(file).unwrap();
+        assert!(meta.is_file && !meta.is_dir && !meta.is_symlink);
+        assert_eq!(meta.size, 5);
+        assert!(meta.modified.is_some());
+        assert_eq!(meta.symlink_target, None);
+
+        let meta = file_metadata(path_in(&dir, "")).unwrap();
+        assert!(meta.is_dir && !meta.is_file && !meta.is_symlink);
+        assert_eq!(meta.size, 0);
+        assert!(!meta.executable);
+
+        let missing = file_metadata(path_in(&dir, "nope")).err().unwrap();
+        assert!(missing.ends_with("does not exist"), "{missing}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_metadata_follows_a_symlink_but_reports_it() {
+        let dir = TempDir::new().unwrap();
+        let target = path_in(&dir, "target.txt");
+        fs::write(&target, "abc").unwrap();
+        let link = path_in(&dir, "link");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let meta = file_metadata(link).unwrap();
+        assert!(meta.is_symlink && meta.is_file);
+        assert_eq!(meta.size, 3);
+        assert_eq!(meta.symlink_target, Some(target));
+
+        // A dangling link still describes itself
+        let dangling = path_in(&dir, "dangling");
+        std::os::unix::fs::symlink(path_in(&dir, "gone"), &dangling).unwrap();
+        let meta = file_metadata(dangling).unwrap();
+        assert!(meta.is_symlink && !meta.is_file && !meta.is_dir);
+    }
+
+    #[test]
+    fn move_path_renames_a_tree_on_the_same_device() {
+        let dir = TempDir::new().unwrap();
+        let source = dir.path().join("src");
+        fs::create_dir_all(source.join("nested")).unwrap();
+        fs::write(source.join("nested/a.txt"), "a").unwrap();
+        let target = dir.path().join("moved");
+
+        move_path(&source, &target, None).unwrap();
+        assert!(!source.exists());
+        assert_eq!(
+            fs::read_to_string(target.join("nested/a.txt")).unwrap(),
+            "a"
+        );
+    }
+
+    #[test]
+    fn move_by_copy_reports_progress_and_removes_the_source() {
+        let dir = TempDir::new().unwrap();
+        let source = dir.path().join("src");
+        fs::create_dir_all(source.join("nested")).unwrap();
+        fs::write(source.join("one.txt"), "123").unwrap();
+        fs::write(source.join("nested/two.txt"), "4567").unwrap();
+        let target = dir.path().join("moved");
+
+        let mut updates = Vec::new();
+        move_by_copy(&source, &target, &mut |p| {
+            updates.push((p.copied_bytes, p.total_bytes))
+        })
+        .unwrap();
+
+        assert!(!source.exists());
+        assert_eq!(fs::read_to_string(target.join("one.txt")).unwrap(), "123");
+        assert_eq!(
+            fs::read_to_string(target.join("nested/two.txt")).unwrap(),
+            "4567"
+        );
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates.last(), Some(&(7, 7)));
+        // Nothing is left staged next to the target
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn a_failed_move_by_copy_keeps_the_source() {
+        let dir = TempDir::new().unwrap();
+        let source = dir.path().join("a.txt");
+        fs::write(&source, "a").unwrap();
+        let target = dir.path().join("missing/a.txt");
+
+        assert!(move_by_copy(&source, &target, &mut |_| {}).is_err());
+        assert_eq!(fs::read_to_string(&source).unwrap(), "a");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn set_executable_flips_only_the_execute_bits() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = TempDir::new().unwrap();
+        let script = path_in(&dir, "run.sh");
+        fs::write(&script, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o640)).unwrap();
+        let mode = || fs::metadata(&script).unwrap().permissions().mode() & 0o777;
+
+        set_executable(script.clone(), true).unwrap();
+        assert_eq!(mode(), 0o751);
+        assert!(file_metadata(script.clone()).unwrap().executable);
+
+        set_executable(script.clone(), false).unwrap();
+        assert_eq!(mode(), 0o640);
+        assert!(!file_metadata(script.clone()).unwrap().executable);
+
+        assert!(set_executable(path_in(&dir, ""), true).is_err());
+        assert!(set_executable(path_in(&dir, "missing"), true).is_err());
+    }
+
+    #[test]
+    fn copy_entry_copies_a_nested_tree_with_its_times() {
+        let dir = TempDir::new().unwrap();
+        let source = dir.path().join("src");
+        fs::create_dir_all(source.join("a/b")).unwrap();
+        fs::write(source.join("top.txt"), "top").unwrap();
+        fs::write(source.join("a/b/deep.txt"), "deep").unwrap();
+        fs::create_dir(source.join("empty")).unwrap();
+        let old = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        fs::File::options()
+            .write(true)
+            .open(source.join("top.txt"))
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+
+        let target = path_in(&dir, "copy");
+        copy_entry(path_in(&dir, "src"), target.clone(), false).unwrap();
+
+        let target = Path::new(&target);
+        assert_eq!(fs::read_to_string(target.join("top.txt")).unwrap(), "top");
+        assert_eq!(
+            fs::read_to_string(target.join("a/b/deep.txt")).unwrap(),
+            "deep"
+        );
+        assert!(target.join("empty").is_dir());
+        let modified = fs::metadata(target.join("top.txt"))
+            .unwrap()
+            .modified()
+            .unwrap();
+        assert_eq!(modified, old);
+        // The source is untouched
+        assert_eq!(
+            fs::read_to_string(source.join("a/b/deep.txt")).unwrap(),
+            "deep"
+        );
+        assert!(copy_entry(path_in(&dir, "src"), path_in(&dir, "copy"), false).is_err());
+    }
+
+    #[test]
+    fn copy_entry_refuses_to_copy_into_or_over_itself() {
+        let dir = TempDir::new().unwrap();
+        let source = path_in(&dir, "src");
+        fs::create_dir_all(dir.path().join("src/inner")).unwrap();
+        fs::write(dir.path().join("src/inner/a.txt"), "a").unwrap();
+
+        let into = copy_entry(source.clone(), path_in(&dir, "src/inner/copy"), false);
+        assert!(into.unwrap_err().contains("into itself"));
+        let onto = copy_entry(source.clone(), source.clone(), true);
+        assert!(onto.is_err());
+        let over_parent = copy_entry(path_in(&dir, "src/inner"), source.clone(), true);
+        assert!(over_parent.unwrap_err().contains("contains"));
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("src/inner/a.txt")).unwrap(),
+            "a"
+        );
+        assert!(!dir.path().join("src/inner/copy").exists());
+    }
+
+    #[test]
+    fn resolve_expands_a_leading_tilde() {
+        let home = TempDir::new().unwrap();
+        fs::create_dir(home.path().join("code")).unwrap();
+        let real_home = fs::canonicalize(home.path()).unwrap();
+
+        let resolved = resolve("~/code", None, Some(home.path().to_path_buf())).unwrap();
+        assert_eq!(resolved, real_home.join("code"));
+        let resolved = resolve("~", None, Some(home.path().to_path_buf())).unwrap();
+        assert_eq!(resolved, real_home);
+        // Only a whole `~` component is the home directory
+        let resolved = resolve("~code", Some("/base"), Some(home.path().to_path_buf())).unwrap();
+        assert_eq!(resolved, Path::new("/base/~code"));
+        assert!(resolve("~/code", None, None).is_err());
+    }
+
+    #[test]
+    fn resolve_collapses_dots_in_existing_and_missing_paths() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        let real = fs::canonicalize(dir.path()).unwrap();
+        let base = real.to_string_lossy().to_string();
+
+        let existing = resolve("a/b/../b/./", Some(&base), None).unwrap();
+        assert_eq!(existing, real.join("a/b"));
+
+        // Nothing on disk, so the collapse is purely lexical
+        let missing = resolve("a/missing/../gone/./x.txt", Some(&base), None).unwrap();
+        assert_eq!(missing, real.join("a/gone/x.txt"));
+        let above_root = resolve("/../../nowhere-at-all", None, None).unwrap();
+        assert_eq!(above_root, Path::new("/nowhere-at-all"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn real_case_keeps_components_under_an_unreadable_directory() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = TempDir::new().unwrap();
+        let locked = dir.path().join("Locked");
+        fs::create_dir(&locked).unwrap();
+        fs::write(locked.join("Inner.txt"), "x").unwrap();
+        // Searchable but not listable
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o311)).unwrap();
+
+        let real = real_case(&locked.join("Inner.txt"));
+        // Put back before asserting, so the temp dir can still be removed
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert_eq!(real.unwrap(), locked.join("Inner.txt"));
+    }
+
+    #[test]
+    fn files_with_nul_bytes_are_binary_and_text_is_not() {
+        let dir = TempDir::new().unwrap();
+        let cases: [(&str, &[u8], bool); 4] = [
+            ("text.txt", b"fn main() {}\n", false),
+            ("utf8.txt", "caf\u{e9} \u{2713}\n".as_bytes(), false),
+            ("empty.txt", b"", false),
+            ("image.bin", b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR", true),
+        ];
+        for (name, bytes, binary) in cases {
+            let path = path_in(&dir, name);
+            fs::write(&path, bytes).unwrap();
+            assert_eq!(is_binary_file(path).unwrap(), binary, "{name}");
+        }
+        assert!(is_binary_file(path_in(&dir, "missing")).is_err());
+    }
+
+    /// Whether names in `dir` are matched ignoring case, as on default
+    /// macOS and Windows volumes.
+    fn ignores_case(dir: &TempDir) -> bool {
+        fs::write(dir.path().join("Probe"), "").unwrap();
+        let folded = dir.path().join("PROBE").exists();
+        fs::remove_file(dir.path().join("Probe")).unwrap();
+        folded
+    }
+
+    #[test]
+    fn real_case_spells_each_component_as_on_disk() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("project/src")).unwrap();
+        if !ignores_case(&dir) {
+            // Only an exact spelling opens anything here
+            return;
+        }
+        let real = real_case(&dir.path().join("PROJECT/SRC")).unwrap();
+        assert_eq!(real, dir.path().join("project/src"));
+    }
+
+    #[test]
+    fn real_case_prefers_an_exact_match_over_a_folded_one() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("project")).unwrap();
+        if ignores_case(&dir) {
+            // `PROJECT` can't exist next to `project`
+            return;
+        }
+        fs::create_dir(dir.path().join("PROJECT")).unwrap();
+        let real = real_case(&dir.path().join("PROJECT")).unwrap();
+        assert_eq!(real, dir.path().join("PROJECT"));
+    }
+
+    #[test]
+    fn a_failed_atomic_write_leaves_the_target_and_no_temp_file() {
+        let dir = TempDir::new().unwrap();
+        // Renaming a file over a non-empty directory fails
+        let target = dir.path().join("taken");
+        fs::create_dir(&target).unwrap();
+        fs::write(target.join("inside.txt"), "kept").unwrap();
+
+        assert!(write_atomic(&target, b"new").is_err());
+        assert_eq!(
+            fs::read_to_string(target.join("inside.txt")).unwrap(),
+            "kept"
+        );
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,4695 @@
+use crate::files::tree_size;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, BufReader, ErrorKind, Read, Write};
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::ipc::Channel;
+use thiserror::Error;
+
+/// Errors from the git commands. Serialized as `{ "kind": "...", ...fields }`
+/// so the frontend can branch on `kind` instead of matching git's stderr.
+#[derive(Debug, Error, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum GitError {
+    #[error("git is not installed or not on PATH")]
+    GitNotInstalled,
+    #[error("not a git repository")]
+    NotARepository,
+    #[error("already inside a git repository")]
+    AlreadyARepository,
+    #[error("'{file}' does not exist")]
+    FileNotFound { file: String },
+    #[error("a branch named '{branch}' already exists")]
+    BranchExists { branch: String },
+    #[error("'{branch}' is the current branch")]
+    BranchIsCurrent { branch: String },
+    #[error("no remote named '{remote}'")]
+    RemoteNotFound { remote: String },
+    #[error("a remote named '{remote}' already exists")]
+    RemoteExists { remote: String },
+    #[error("'{rev}' does not name a git object")]
+    UnknownRevision { rev: String },
+    #[error("'{rev}' is not a known commit")]
+    UnknownCommit { rev: String },
+    #[error("invalid start point '{start_point}'")]
+    InvalidStartPoint { start_point: String },
+    #[error("nothing to commit")]
+    NothingToCommit,
+    #[error("no local changes to stash")]
+    NothingToStash,
+    #[error("no merge, rebase, cherry-pick or revert is in progress")]
+    NoOperationInProgress,
+    #[error("unresolved conflicts in {}", files.join(", "))]
+    UnresolvedConflicts { files: Vec<String> },
+    /// The remote rejected our credentials, or would have needed a prompt.
+    #[error("authentication failed: {stderr}")]
+    AuthenticationFailed { stderr: String },
+    #[error("{feature} needs a newer version of git")]
+    GitTooOld { feature: String },
+    /// A network command printed nothing, not even progress, for this long.
+    #[error("git made no progress for {seconds} seconds")]
+    Timeout { seconds: u64 },
+    #[error("cancelled")]
+    Cancelled,
+    /// There's no usable trash for `file`; deleting it needs `permanent`.
+    #[error("couldn't move '{file}' to the trash: {message}")]
+    TrashFailed { file: String, message: String },
+    /// Rejected before running git because an argument was unusable.
+    #[error("{message}")]
+    InvalidArgument { message: String },
+    /// Git ran but failed; `stderr` is its own explanation.
+    #[error("{stderr}")]
+    CommandFailed { stderr: String },
+    #[error("failed to run git: {message}")]
+    Io { message: String },
+}
+
+impl From<io::Error> for GitError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            ErrorKind::NotFound => GitError::GitNotInstalled,
+            _ => GitError::Io {
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+impl GitError {
+    /// Maps the stderr of a failed git invocation to the most specific
+    /// variant. Git runs with `LC_ALL=C`, so these messages are stable.
+    fn from_stderr(stderr: &[u8]) -> Self {
+        let stderr = String::from_utf8_lossy(stderr).trim().to_string();
+        if stderr.contains("not a git repository") {
+            return GitError::NotARepository;
+        }
+        if let Some(branch) = stderr
+            .strip_prefix("fatal: a branch named '")
+            .and_then(|rest| rest.strip_suffix("' already exists"))
+        {
+            return GitError::BranchExists {
+                branch: branch.to_string(),
+            };
+        }
+        if let Some(remote) = stderr
+            .split_once("No such remote: '")
+            .and_then(|(_, rest)| rest.strip_suffix('\''))
+        {
+            return GitError::RemoteNotFound {
+                remote: remote.to_string(),
+            };
+        }
+        if let Some(remote) = stderr
+            .strip_prefix("error: remote ")
+            .and_then(|rest| rest.strip_suffix(" already exists."))
+        {
+            return GitError::RemoteExists {
+                remote: remote.to_string(),
+            };
+        }
+        if stderr.contains("Authentication failed")
+            || stderr.contains("terminal prompts disabled")
+            || stderr.contains("Permission denied (publickey")
+            || stderr.contains("Host key verification failed")
+        {
+            return GitError::AuthenticationFailed { stderr };
+        }
+        GitError::CommandFailed { stderr }
+    }
+}
+
+/// How long fetch/pull/push may go without printing anything before the
+/// connection counts as stalled. They run with `--progress`, which reports
+/// at least once a second while a transfer moves, so a big but live
+/// transfer never hits this.
+const NETWORK_IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// One line of `git status --porcelain`. The status chars are git's `X`/`Y`
+/// codes, e.g. `M`, `A`, `D`, `R`, `?` or a space for "unchanged".
+#[derive(Clone, Serialize)]
+pub struct FileStatus {
+    pub path: String,
+    /// The path before a rename or copy (`R`/`C` in the index column).
+    pub orig_path: Option<String>,
+    pub index_status: char,
+    pub worktree_status: char,
+    pub is_untracked: bool,
+}
+
+/// `git_status_files` sorted into the sections of a changes panel.
+#[derive(Serialize)]
+pub struct StatusGroups {
+    pub staged: Vec<FileStatus>,
+    /// Changes in the working tree not yet staged. A file staged and then
+    /// edited again is in both this and `staged`.
+    pub unstaged: Vec<FileStatus>,
+    pub untracked: Vec<String>,
+    /// Files with unresolved merge conflicts, which are in no other group.
+    pub conflicted: Vec<String>,
+}
+
+/// A file or directory git ignores, with how much disk it takes.
+#[derive(Serialize)]
+pub struct IgnoredEntry {
+    /// Relative to the path passed in.
+    pub path: String,
+    pub is_dir: bool,
+    /// Bytes in the file, or in all regular files below the directory.
+    pub size: u64,
+}
+
+/// What HEAD points at. A detached HEAD gets its own variant so it's never
+/// mistaken for a branch literally named `HEAD`.
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum BranchRef {
+    Named { name: String },
+    Detached { short_sha: String },
+}
+
+#[derive(Serialize)]
+pub struct BranchInfo {
+    /// Short name, e.g. `main` or `origin/main`.
+    pub name: String,
+    pub is_current: bool,
+    /// True for remote-tracking branches.
+    pub is_remote: bool,
+    /// Short name of the upstream, for local branches that have one.
+    pub upstream: Option<String>,
+    pub last_commit_sha: String,
+    pub last_commit_subject: String,
+    /// Committer date in unix seconds.
+    pub last_commit_date: i64,
+}
+
+#[derive(Serialize)]
+pub struct UpstreamStatus {
+    /// e.g. `origin/main`; `None` when the branch doesn't track anything.
+    pub upstream: Option<String>,
+    pub ahead: u32,
+    pub behind: u32,
+}
+
+/// Vitals for a repository dashboard.
+#[derive(Serialize)]
+pub struct RepoSummary {
+    pub current_branch: BranchRef,
+    /// `None` before the first commit.
+    pub head_sha: Option<String>,
+    /// Commits reachable from HEAD.
+    pub total_commits: u32,
+    /// Local branches.
+    pub branch_count: u32,
+    pub tag_count: u32,
+    /// Any staged, unstaged or untracked change.
+    pub dirty: bool,
+    /// Relative to the upstream; 0 without one.
+    pub ahead: u32,
+    pub behind: u32,
+}
+
+/// How two refs relate, from `a`'s point of view.
+#[derive(Serialize)]
+pub struct RefComparison {
+    /// Commits reachable from `a` but not `b`.
+    pub ahead: u32,
+    /// Commits reachable from `b` but not `a`.
+    pub behind: u32,
+    /// `None` when the histories are unrelated.
+    pub merge_base: Option<String>,
+}
+
+/// The config file a setting was read from.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConfigScope {
+    /// The repository's own `.git/config`.
+    Local,
+    /// The user's `~/.gitconfig`.
+    Global,
+    /// The machine-wide config.
+    System,
+}
+
+/// Who commits in a repo would be attributed to.
+#[derive(Serialize)]
+pub struct GitIdentity {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    /// Where `name` comes from, or `email` when there's no name; `None` when
+    /// neither is set anywhere.
+    pub source: Option<ConfigScope>,
+}
+
+#[derive(Serialize)]
+pub struct StashEntry {
+    /// N in `stash@{N}`; 0 is the most recent.
+    pub index: u32,
+    pub message: String,
+    /// Branch the stash was made on; `None` if HEAD was detached.
+    pub branch: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Worktree {
+    pub path: String,
+    /// Short branch name; `None` when detached or bare.
+    pub branch: Option<String>,
+    /// `None` for a bare repository.
+    pub head_sha: Option<String>,
+    /// The repository's own working tree, which can't be locked or removed.
+    pub is_main: bool,
+    pub is_bare: bool,
+    pub is_detached: bool,
+    pub is_locked: bool,
+}
+
+/// How a submodule's checkout compares with the commit the superproject
+/// records for it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SubmoduleState {
+    UpToDate,
+    /// A different commit is checked out than the one recorded.
+    OutOfSync,
+    /// Never cloned, so its folder is empty.
+    Uninitialized,
+    /// The recorded commit has merge conflicts.
+    Conflicted,
+}
+
+#[derive(Serialize)]
+pub struct Submodule {
+    /// Relative to the top level of the superproject.
+    pub path: String,
+    /// The checked-out commit, or the recorded one when uninitialized.
+    pub sha: String,
+    /// The branch `.gitmodules` says to track, if any.
+    pub branch: Option<String>,
+    pub state: SubmoduleState,
+}
+
+#[derive(Serialize)]
+pub struct Remote {
+    pub name: String,
+    pub fetch_url: String,
+    /// Usually the same as `fetch_url` unless a `pushurl` is configured.
+    pub push_url: String,
+}
+
+#[derive(Serialize)]
+pub struct Tag {
+    pub name: String,
+    /// The commit the tag points at, peeled through annotated tag objects.
+    pub target_sha: String,
+    pub is_annotated: bool,
+    /// The annotation; always `None` for lightweight tags.
+    pub message: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct BlameLine {
+    /// 1-based line number in the current file.
+    pub line_number: u32,
+    pub sha: String,
+    pub author: String,
+    /// Author date in unix epoch seconds.
+    pub timestamp: i64,
+    pub content: String,
+    /// True for lines changed locally but not yet committed, which git
+    /// attributes to an all-zero SHA.
+    pub uncommitted: bool,
+}
+
+/// Commits for blame to look past, such as bulk reformatting, so lines are
+/// attributed to whoever last changed them meaningfully. Both fields need
+/// git 2.23.
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BlameOptions {
+    /// A file listing commits to ignore, one per line, like the common
+    /// `.git-blame-ignore-revs`. Relative to the repository path.
+    pub ignore_revs_file: Option<String>,
+    pub ignore_revs: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct Commit {
+    pub sha: String,
+    pub short_sha: String,
+    pub author_name: String,
+    pub author_email: String,
+    /// Author date in unix epoch seconds.
+    pub timestamp: i64,
+    pub subject: String,
+    pub body: String,
+    /// Parent SHAs; empty for a root commit, two or more for a merge.
+    pub parents: Vec<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ObjectType {
+    Commit,
+    Tree,
+    Blob,
+    /// An annotated tag.
+    Tag,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RefKind {
+    Head,
+    Branch,
+    RemoteBranch,
+    Tag,
+}
+
+/// A ref to label in a commit graph.
+#[derive(Serialize)]
+pub struct GraphRef {
+    /// Short name such as `main`, `origin/main` or `v1.0`; `HEAD` for HEAD.
+    pub name: String,
+    /// The commit it points at, peeled for annotated tags.
+    pub sha: String,
+    pub kind: RefKind,
+}
+
+#[derive(Serialize)]
+pub struct GraphData {
+    /// Newest first, but never a commit before one of its children.
+    pub commits: Vec<Commit>,
+    /// The refs pointing at one of `commits`.
+    pub refs: Vec<GraphRef>,
+}
+
+#[derive(Serialize)]
+pub struct ChangedFile {
+    pub path: String,
+    /// The old path for a rename or copy.
+    pub orig_path: Option<String>,
+    /// Git's status letter: `A`, `M`, `D`, `R`, `C`, `T`.
+    pub status: String,
+    /// Line counts; `None` for binary files.
+    pub additions: Option<u32>,
+    pub deletions: Option<u32>,
+}
+
+/// A commit in a file's history, with the file's path as of that commit.
+#[derive(Serialize)]
+pub struct FileRevision {
+    #[serde(flatten)]
+    pub commit: Commit,
+    pub path: String,
+}
+
+#[derive(Serialize)]
+pub struct CommitDetail {
+    #[serde(flatten)]
+    pub commit: Commit,
+    pub files: Vec<ChangedFile>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum MergeResult {
+    /// Nothing to merge; HEAD already contains the branch.
+    UpToDate {
+        sha: String,
+    },
+    FastForward {
+        sha: String,
+    },
+    /// A merge commit was created.
+    Merged {
+        sha: String,
+    },
+    /// The merge stopped with these files unresolved. The repo is left
+    /// mid-merge for the user to resolve or abort.
+    Conflicts {
+        files: Vec<String>,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum RebaseResult {
+    /// Nothing to replay; the branch already contains `onto`.
+    UpToDate { sha: String },
+    /// Every commit was replayed; the branch now ends at `sha`.
+    Completed { sha: String },
+    /// Replaying commit `step` of `total` (1-based) stopped with these files
+    /// unresolved. The rebase is left in progress for
+    /// `git_operation_continue` or `git_operation_abort`.
+    Conflicts {
+        step: u32,
+        total: u32,
+        files: Vec<String>,
+    },
+}
+
+/// Outcome of replaying one commit with `git_cherry_pick` or `git_revert`.
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ApplyResult {
+    /// The change was committed as `sha`.
+    Committed { sha: String },
+    /// With `no_commit`: the change is staged but not committed.
+    Staged,
+    /// The operation stopped with these files unresolved and is left in
+    /// progress for the user to resolve or abort.
+    Conflicts { files: Vec<String> },
+}
+
+/// A multi-step operation git has paused, usually on conflicts.
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Operation {
+    Merge,
+    Rebase,
+    CherryPick,
+    Revert,
+}
+
+impl Operation {
+    /// The git subcommand that takes this operation's `--abort` and
+    /// `--continue`.
+    fn command(self) -> &'static str {
+        match self {
+            Operation::Merge => "merge",
+            Operation::Rebase => "rebase",
+            Operation::CherryPick => "cherry-pick",
+            Operation::Revert => "revert",
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ConflictState {
+    pub files: Vec<String>,
+    pub in_progress: Option<Operation>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ResetMode {
+    /// Move HEAD only; the index and working tree keep their changes.
+    Soft,
+    /// Move HEAD and reset the index; changes stay in the working tree.
+    Mixed,
+    /// Move HEAD and overwrite index and working tree. Uncommitted changes
+    /// to tracked files are lost for good.
+    Hard,
+}
+
+/// One progress update from `git clone`, e.g. phase `Receiving objects` at
+/// 42%. Server-side phases keep git's `remote: ` prefix stripped.
+#[derive(Clone, PartialEq, Serialize)]
+pub struct CloneProgress {
+    pub phase: String,
+    pub percent: u32,
+}
+
+/// `--pretty` format for `Commit`, used together with `-z`. Fields and
+/// commits are all NUL-separated, which can't appear in commit text, so
+/// multi-line bodies parse safely.
+const LOG_FORMAT: &str = "--pretty=format:%H%x00%h%x00%an%x00%ae%x00%at%x00%P%x00%s%x00%b";
+const LOG_FIELDS: usize = 8;
+
+/// `LOG_FORMAT` for logs with `--name-only`: each commit starts with 0x1e
+/// and the body gets its own NUL, so the file names that follow can be told
+/// apart from it.
+const FILE_LOG_FORMAT: &str =
+    "--pretty=format:%x1e%H%x00%h%x00%an%x00%ae%x00%at%x00%P%x00%s%x00%b%x00";
+
+/// `git -C <path> <args>`, with messages forced to English so
+/// `GitError::from_stderr` can recognise them.
+fn git_command(path: &str, args: &[&str]) -> Command {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(path).args(args).env("LC_ALL", "C");
+    command
+}
+
+/// Runs git and returns its raw output, whatever the exit status.
+fn git_output(path: &str, args: &[&str]) -> Result<Output, GitError> {
+    Ok(git_command(path, args).output()?)
+}
+
+/// Runs git and returns stdout on success, or the classified stderr.
+fn run_git(path: &str, args: &[&str]) -> Result<String, GitError> {
+    let output = git_output(path, args)?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(GitError::from_stderr(&output.stderr))
+    }
+}
+
+/// Like `run_git`, but returns stdout as it is, for file contents.
+fn run_git_bytes(path: &str, args: &[&str]) -> Result<Vec<u8>, GitError> {
+    let output = git_output(path, args)?;
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(GitError::from_stderr(&output.stderr))
+    }
+}
+
+/// Like `run_git`, but feeds `input` to git's stdin. Used for anything that
+/// can contain arbitrary user text, so it never goes through argv.
+fn run_git_with_stdin(path: &str, args: &[&str], input: &str) -> Result<String, GitError> {
+    let mut child = git_command(path, args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes())?;
+    }
+
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(GitError::from_stderr(&output.stderr))
+    }
+}
+
+/// A git command that talks to a remote. Nothing may prompt, since there's
+/// no terminal to answer: `GIT_TERMINAL_PROMPT=0` makes HTTPS fail instead
+/// of asking for a username, and unless the user has their own
+/// `GIT_SSH_COMMAND`, ssh runs in batch mode so it fails instead of asking
+/// for a passphrase or host-key confirmation. Credential helpers and agents
+/// still work.
+fn network_command(path: &str, args: &[&str]) -> Command {
+    let mut command = git_command(path, args);
+    command.env("GIT_TERMINAL_PROMPT", "0").stdin(Stdio::null());
+    if std::env::var_os("GIT_SSH_COMMAND").is_none() {
+        command.env("GIT_SSH_COMMAND", "ssh -o BatchMode=yes");
+    }
+    command
+}
+
+/// Runs a network git command, killing it once it has printed nothing for
+/// `idle_timeout`. Pass `--progress` in `args` so a working transfer keeps
+/// talking. Returns git's stdout and stderr together, since that's where
+/// the remote's summary goes, minus the progress lines.
+fn run_git_network(path: &str, args: &[&str], idle_timeout: Duration) -> Result<String, GitError> {
+    let mut child = network_command(path, args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Drain both pipes on their own threads so a chatty git can't block on a
+    // full pipe while we wait for it
+    let activity = Arc::new(Mutex::new(Instant::now()));
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let stdout = thread::spawn({
+        let activity = activity.clone();
+        move || read_tracking(stdout, &activity)
+    });
+    let stderr = thread::spawn({
+        let activity = activity.clone();
+        move || read_tracking(stderr, &activity)
+    });
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if activity.lock().unwrap().elapsed() >= idle_timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(GitError::Timeout {
+                seconds: idle_timeout.as_secs(),
+            });
+        }
+        thread::sleep(Duration::from_millis(100));
+    };
+
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = without_progress(&stderr.join().unwrap_or_default());
+    if !status.success() {
+        return Err(GitError::from_stderr(stderr.as_bytes()));
+    }
+
+    let mut summary = String::from_utf8_lossy(&stdout).into_owned();
+    summary.push_str(&stderr);
+    Ok(summary.trim().to_string())
+}
+
+/// Reads `pipe` to the end, stamping `activity` whenever output arrives.
+fn read_tracking(pipe: Option<impl Read>, activity: &Mutex<Instant>) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let Some(mut pipe) = pipe else {
+        return buf;
+    };
+    let mut chunk = [0; 4096];
+    loop {
+        match pipe.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => {
+                buf.extend_from_slice(&chunk[..n]);
+                *activity.lock().unwrap() = Instant::now();
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(_) => break,
+        }
+    }
+    buf
+}
+
+/// `stderr` without the lines `--progress` adds, such as `Receiving
+/// objects:  42% (420/1000)` and the final `Counting objects: 5, done.`
+fn without_progress(stderr: &[u8]) -> String {
+    let stderr = String::from_utf8_lossy(stderr);
+    let mut lines = Vec::new();
+    for line in stderr.split(['\r', '\n']) {
+        // The remote pads its lines to overwrite longer ones
+        let line = line.trim_end();
+        let done = line
+            .strip_prefix("remote: ")
+            .unwrap_or(line)
+            .split_once(": ")
+            .is_some_and(|(_, rest)| rest.starts_with(|c: char| c.is_ascii_digit()))
+            && line.ends_with(", done.");
+        if line.is_empty() || done || parse_clone_progress(line).is_some() {
+            continue;
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+/// Runs blocking git work off the main thread for the async commands.
+async fn run_blocking<T, F>(f: F) -> Result<T, GitError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, GitError> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(f)
+        .await
+        .map_err(|e| GitError::Io {
+            message: e.to_string(),
+        })?
+}
+
+/// Runs `read` against the repository at `path` in-process with libgit2,
+/// saving a git process on the hot read-only commands. `None` when libgit2
+/// can't open or read the repository: not a repository at all, or one using
+/// something libgit2 doesn't support, such as a newer object format. The
+/// caller then falls back to the git CLI, which also reports the usual
+/// errors.
+fn with_libgit2<T>(
+    path: &str,
+    read: impl FnOnce(&git2::Repository) -> Result<T, git2::Error>,
+) -> Option<T> {
+    let repo = git2::Repository::discover(path).ok()?;
+    read(&repo).ok()
+}
+
+/// Refuses a rev that git would parse as an option, like
+/// `--output=<file>`, before it reaches argv.
+fn validate_rev(rev: &str) -> Result<(), GitError> {
+    if rev.is_empty() || rev.starts_with('-') {
+        return Err(GitError::UnknownRevision {
+            rev: rev.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Refuses a remote that git would parse as an option, like
+/// `--upload-pack=<command>`, which network commands would run.
+fn validate_remote(remote: &str) -> Result<(), GitError> {
+    if remote.is_empty() || remote.starts_with('-') {
+        return Err(GitError::InvalidArgument {
+            message: format!("'{remote}' is not a valid remote"),
+        });
+    }
+    Ok(())
+}
+
+/// Name of the checked-out branch, or `None` when HEAD is detached.
+fn current_branch_name(path: &str) -> Result<Option<String>, GitError> {
+    let output = git_output(path, &["symbolic-ref", "--quiet", "--short", "HEAD"])?;
+    if output.status.success() {
+        Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ))
+    } else if output.stderr.is_empty() {
+        // `--quiet` only silences the detached case
+        Ok(None)
+    } else {
+        Err(GitError::from_stderr(&output.stderr))
+    }
+}
+
+/// The checked-out branch, or the short SHA when HEAD is detached (e.g.
+/// after checking out a tag). An unborn branch in a new repo is still named.
+#[tauri::command]
+pub fn git_current_branch(path: String) -> Result<BranchRef, GitError> {
+    if let Some(branch) = with_libgit2(&path, libgit2_current_branch) {
+        return Ok(branch);
+    }
+    if let Some(name) = current_branch_name(&path)? {
+        return Ok(BranchRef::Named { name });
+    }
+    let short_sha = run_git(&path, &["rev-parse", "--short", "HEAD"])?;
+    Ok(BranchRef::Detached {
+        short_sha: short_sha.trim().to_string(),
+    })
+}
+
+fn libgit2_current_branch(repo: &git2::Repository) -> Result<BranchRef, git2::Error> {
+    let head = repo.find_reference("HEAD")?;
+    // Also covers an unborn branch, whose ref doesn't exist yet
+    if let Some(target) = head.symbolic_target() {
+        let name = target.strip_prefix("refs/heads/").unwrap_or(target);
+        return Ok(BranchRef::Named {
+            name: name.to_string(),
+        });
+    }
+    let short_sha = head.peel_to_commit()?.as_object().short_id()?;
+    Ok(BranchRef::Detached {
+        short_sha: short_sha.as_str().unwrap_or_default().to_string(),
+    })
+}
+
+/// Switches the working tree to `branch`. If local changes would be
+/// overwritten git refuses, and its message is passed through as the error.
+#[tauri::command]
+pub fn git_checkout_branch(path: String, branch: String) -> Result<(), GitError> {
+    validate_rev(&branch)?;
+    // The trailing `--` stops git from treating `branch` as a file path
+    run_git(&path, &["checkout", &branch, "--"]).map(|_| ())
+}
+
+/// Creates `new_branch` at `start_point` (a branch, tag or SHA) without
+/// checking it out.
+#[tauri::command]
+pub fn git_create_branch_from(
+    path: String,
+    new_branch: String,
+    start_point: String,
+) -> Result<(), GitError> {
+    if resolve_commit(&path, &start_point)?.is_none() {
+        return Err(GitError::InvalidStartPoint { start_point });
+    }
+    run_git(&path, &["branch", "--", &new_branch, &start_point]).map(|_| ())
+}
+
+/// Creates `branch` at HEAD without checking it out.
+#[tauri::command]
+pub fn git_create_branch(path: String, branch: String) -> Result<(), GitError> {
+    git_create_branch_from(path, branch, "HEAD".into())
+}
+
+/// Deletes a local branch. Without `force` git refuses to drop a branch with
+/// unmerged commits (`-d`); with it the branch goes regardless (`-D`).
+#[tauri::command]
+pub fn git_delete_branch(path: String, branch: String, force: bool) -> Result<(), GitError> {
+    if current_branch_name(&path)?.as_deref() == Some(branch.as_str()) {
+        return Err(GitError::BranchIsCurrent { branch });
+    }
+
+    let flag = if force { "-D" } else { "-d" };
+    run_git(&path, &["branch", flag, "--", &branch]).map(|_| ())
+}
+
+/// Parses `git status --porcelain=v1 -z`. Records are NUL-terminated and
+/// unquoted, so paths with spaces or unicode need no unescaping; a rename or
+/// copy is followed by an extra record holding the original path.
+fn parse_porcelain_z(output: &str) -> Vec<FileStatus> {
+    let mut records = output.split('\0').filter(|r| !r.is_empty());
+    let mut files = Vec::new();
+
+    while let Some(record) = records.next() {
+        let mut chars = record.chars();
+        let (Some(index_status), Some(worktree_status)) = (chars.next(), chars.next()) else {
+            continue;
+        };
+        let Some(path) = record.get(3..) else {
+            continue;
+        };
+
+        let orig_path = if matches!(index_status, 'R' | 'C') || matches!(worktree_status, 'R' | 'C')
+        {
+            records.next().map(str::to_string)
+        } else {
+            None
+        };
+
+        files.push(FileStatus {
+            path: path.to_string(),
+            orig_path,
+            index_status,
+            worktree_status,
+            is_untracked: index_status == '?',
+        });
+    }
+
+    files
+}
+
+/// The porcelain code of an unmerged path, from which of the three merge
+/// stages the index holds for it.
+fn conflict_code(conflict: &git2::IndexConflict) -> (char, char) {
+    let stages = (
+        conflict.ancestor.is_some(),
+        conflict.our.is_some(),
+        conflict.their.is_some(),
+    );
+    match stages {
+        (true, true, true) => ('U', 'U'),
+        (false, true, true) => ('A', 'A'),
+        (true, true, false) => ('U', 'D'),
+        (true, false, true) => ('D', 'U'),
+        (false, true, false) => ('A', 'U'),
+        (false, false, true) => ('U', 'A'),
+        _ => ('D', 'D'),
+    }
+}
+
+/// `git status --porcelain=v1` read through libgit2: untracked directories
+/// are collapsed to `dir/` and renames are detected in the index only, as
+/// git does by default. Unlike git, `status.showUntrackedFiles` and
+/// `status.renames` from the user's config aren't applied.
+fn libgit2_status(repo: &git2::Repository) -> Result<Vec<FileStatus>, git2::Error> {
+    let mut options = git2::StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(false)
+        .renames_head_to_index(true);
+    let statuses = repo.statuses(Some(&mut options))?;
+
+    let mut conflicts = HashMap::new();
+    if statuses
+        .iter()
+        .any(|e| e.status().contains(git2::Status::CONFLICTED))
+    {
+        for conflict in repo.index()?.conflicts()? {
+            let conflict = conflict?;
+            let entry = [&conflict.our, &conflict.their, &conflict.ancestor]
+                .into_iter()
+                .find_map(Option::as_ref);
+            if let Some(entry) = entry {
+                let path = String::from_utf8_lossy(&entry.path).into_owned();
+                conflicts.insert(path, conflict_code(&conflict));
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    for entry in statuses.iter() {
+        let status = entry.status();
+        let mut path = String::from_utf8_lossy(entry.path_bytes()).into_owned();
+        let mut orig_path = None;
+        let (index_status, worktree_status) = if let Some(code) = conflicts.get(&path) {
+            *code
+        } else if status.contains(git2::Status::WT_NEW) {
+            ('?', '?')
+        } else {
+            let index_status = if status.contains(git2::Status::INDEX_RENAMED) {
+                if let Some(delta) = entry.head_to_index() {
+                    let file_path = |file: git2::DiffFile| {
+                        file.path().map(|p| p.to_string_lossy().into_owned())
+                    };
+                    orig_path = file_path(delta.old_file());
+                    path = file_path(delta.new_file()).unwrap_or(path);
+                }
+                'R'
+            } else if status.contains(git2::Status::INDEX_NEW) {
+                'A'
+            } else if status.contains(git2::Status::INDEX_MODIFIED) {
+                'M'
+            } else if status.contains(git2::Status::INDEX_DELETED) {
+                'D'
+            } else if status.contains(git2::Status::INDEX_TYPECHANGE) {
+                'T'
+            } else {
+                ' '
+            };
+            let worktree_status = if status.contains(git2::Status::WT_MODIFIED) {
+                'M'
+            } else if status.contains(git2::Status::WT_DELETED) {
+                'D'
+            } else if status.contains(git2::Status::WT_TYPECHANGE) {
+                'T'
+            } else {
+                ' '
+            };
+            (index_status, worktree_status)
+        };
+        if (index_status, worktree_status) == (' ', ' ') {
+            continue;
+        }
+        files.push(FileStatus {
+            path,
+            orig_path,
+            index_status,
+            worktree_status,
+            is_untracked: index_status == '?',
+        });
+    }
+    // Git lists untracked files after every tracked change
+    files.sort_by_key(|f| f.is_untracked);
+    Ok(files)
+}
+
+/// Lists changed, staged and untracked files in the working tree.
+#[tauri::command]
+pub fn git_status_files(path: String) -> Result<Vec<FileStatus>, GitError> {
+    if let Some(files) = with_libgit2(&path, libgit2_status) {
+        return Ok(files);
+    }
+    let output = run_git(&path, &["status", "--porcelain=v1", "-z"])?;
+    Ok(parse_porcelain_z(&output))
+}
+
+/// Like `git_status_files`, but grouped into staged, unstaged, untracked
+/// and conflicted files.
+#[tauri::command]
+pub fn git_status_grouped(path: String) -> Result<StatusGroups, GitError> {
+    let mut groups = StatusGroups {
+        staged: Vec::new(),
+        unstaged: Vec::new(),
+        untracked: Vec::new(),
+        conflicted: Vec::new(),
+    };
+    for file in git_status_files(path)? {
+        // Both sides added or deleted, or either side unmerged
+        let conflicted = matches!(
+            (file.index_status, file.worktree_status),
+            ('D', 'D') | ('A', 'A') | ('U', _) | (_, 'U')
+        );
+        if conflicted {
+            groups.conflicted.push(file.path);
+        } else if file.is_untracked {
+            groups.untracked.push(file.path);
+        } else {
+            if file.worktree_status != ' ' {
+                groups.unstaged.push(file.clone());
+            }
+            if file.index_status != ' ' {
+                groups.staged.push(file);
+            }
+        }
+    }
+    Ok(groups)
+}
+
+/// Whether there are staged or unstaged changes, and with
+/// `include_untracked` untracked files too. Cheaper than `git_status_files`:
+/// git is stopped as soon as it reports the first change.
+#[tauri::command]
+pub fn git_is_dirty(path: String, include_untracked: bool) -> Result<bool, GitError> {
+    let untracked = if include_untracked {
+        "-unormal"
+    } else {
+        "-uno"
+    };
+    let mut child = git_command(&path, &["status", "--porcelain", untracked])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let mut first = [0u8; 1];
+    let read = match child.stdout.take() {
+        Some(mut stdout) => stdout.read(&mut first)?,
+        None => 0,
+    };
+    if read > 0 {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Ok(true);
+    }
+
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        Ok(false)
+    } else {
+        Err(GitError::from_stderr(&output.stderr))
+    }
+}
+
+/// Commits the staged changes and returns the new HEAD SHA. The message goes
+/// through stdin (`-F -`) so multi-line text and leading dashes are safe.
+/// `author` overrides the configured identity, in git's `Name <email>` form.
+///
+/// Fails with `NothingToCommit` when the index matches HEAD and this isn't an
+/// amend.
+#[tauri::command]
+pub fn git_commit(
+    path: String,
+    message: String,
+    amend: bool,
+    author: Option<String>,
+) -> Result<String, GitError> {
+    if !amend {
+        // Exit code 0 means the index has no changes against HEAD
+        let staged = git_output(&path, &["diff", "--cached", "--quiet"])?;
+        if staged.status.success() {
+            return Err(GitError::NothingToCommit);
+        }
+    }
+
+    let mut args = vec!["commit", "-F", "-"];
+    if amend {
+        args.push("--amend");
+    }
+    let author_arg;
+    if let Some(author) = &author {
+        author_arg = format!("--author={author}");
+        args.push(&author_arg);
+    }
+    run_git_with_stdin(&path, &args, &message)?;
+
+    head_sha(&path)
+}
+
+/// Replaces the message of the last commit, leaving its contents alone even
+/// if other changes are staged, and returns the rewritten commit's SHA.
+/// Merge commits are refused. Whether the commit was already pushed can't
+/// be told from here, so rewording a pushed commit is on the caller.
+#[tauri::command]
+pub fn git_amend_message(path: String, message: String) -> Result<String, GitError> {
+    if !has_commits(&path)? {
+        return Err(GitError::InvalidArgument {
+            message: "there is no commit to amend".to_string(),
+        });
+    }
+    let parents = run_git(&path, &["rev-list", "--parents", "-n", "1", "HEAD"])?;
+    if parents.split_whitespace().count() > 2 {
+        return Err(GitError::InvalidArgument {
+            message: "HEAD is a merge commit".to_string(),
+        });
+    }
+
+    // `--only` without paths keeps staged changes out of the amend
+    run_git_with_stdin(&path, &["commit", "--amend", "--only", "-F", "-"], &message)?;
+    head_sha(&path)
+}
+
+/// Stages one file (`git add`). Works for new, modified and deleted files.
+#[tauri::command]
+pub fn git_stage_file(path: String, file: String) -> Result<(), GitError> {
+    run_git(&path, &["add", "--", &file]).map(|_| ())
+}
+
+/// Removes one file from the index, keeping the working-copy changes.
+#[tauri::command]
+pub fn git_unstage_file(path: String, file: String) -> Result<(), GitError> {
+    match run_git(&path, &["restore", "--staged", "--", &file]) {
+        // `git restore` only exists since git 2.23
+        Err(GitError::CommandFailed { stderr }) if stderr.contains("is not a git command") => {
+            run_git(&path, &["reset", "--quiet", "HEAD", "--", &file]).map(|_| ())
+        }
+        result => result.map(|_| ()),
+    }
+}
+
+/// Applies a unified diff, typically one hunk cut from `git_diff`, for
+/// partial staging: `cached` applies it to the index only, `reverse` undoes
+/// it. So `cached` stages a hunk, `cached` + `reverse` unstages one and
+/// `reverse` alone discards it from the working copy. The patch is checked
+/// first, so one that doesn't apply cleanly changes nothing and the error is
+/// git's explanation.
+#[tauri::command]
+pub fn git_apply_patch(
+    path: String,
+    patch: String,
+    cached: bool,
+    reverse: bool,
+) -> Result<(), GitError> {
+    if patch.trim().is_empty() {
+        return Err(GitError::InvalidArgument {
+            message: "patch is empty".into(),
+        });
+    }
+
+    let mut args = vec!["apply"];
+    if cached {
+        args.push("--cached");
+    }
+    if reverse {
+        args.push("--reverse");
+    }
+    let mut check = args.clone();
+    check.push("--check");
+    run_git_with_stdin(&path, &check, &patch)?;
+    run_git_with_stdin(&path, &args, &patch).map(|_| ())
+}
+
+/// Unified diff for one file, of the working copy against the index or, with
+/// `staged`, of the index against HEAD.
+///
+/// Untracked files are invisible to `git diff`, so for those the whole file
+/// is diffed against `/dev/null` and shows up as added. Binary files come back
+/// as git's single `Binary files ... differ` line.
+#[tauri::command]
+pub fn git_diff(path: String, file: String, staged: bool) -> Result<String, GitError> {
+    if !staged {
+        let status = run_git(&path, &["status", "--porcelain=v1", "-z", "--", &file])?;
+        if status.starts_with("??") {
+            // `/dev/null` is passed as-is on every platform: git treats that
+            // exact string as the empty side of a `--no-index` diff, even on
+            // Windows where no such file exists
+            let output = git_output(&path, &["diff", "--no-index", "--", "/dev/null", &file])?;
+            // `--no-index` exits 1 when the inputs differ, which they always do here
+            return match output.status.code() {
+                Some(0 | 1) => Ok(String::from_utf8_lossy(&output.stdout).into_owned()),
+                _ => Err(GitError::from_stderr(&output.stderr)),
+            };
+        }
+    }
+
+    let mut args = vec!["diff"];
+    if staged {
+        args.push("--cached");
+    }
+    args.extend(["--", &file]);
+    run_git(&path, &args)
+}
+
+/// Unified diff of `file` between two refs, or of the whole tree when `file`
+/// is empty. A file only one side has shows up as added or deleted; one
+/// neither has is `FileNotFound`. Binary files come back as git's single
+/// `Binary files ... differ` line, like in `git_diff`.
+#[tauri::command]
+pub fn git_diff_file_refs(
+    path: String,
+    file: String,
+    ref_a: String,
+    ref_b: String,
+) -> Result<String, GitError> {
+    validate_rev(&ref_a)?;
+    validate_rev(&ref_b)?;
+    if file.is_empty() {
+        return run_git(&path, &["diff", &ref_a, &ref_b, "--"]);
+    }
+    let in_a = !run_git(&path, &["ls-tree", &ref_a, "--", &file])?.is_empty();
+    let in_b = !run_git(&path, &["ls-tree", &ref_b, "--", &file])?.is_empty();
+    if !in_a && !in_b {
+        return Err(GitError::FileNotFound { file });
+    }
+    run_git(&path, &["diff", &ref_a, &ref_b, "--", &file])
+}
+
+/// Builds a `Commit` from the `LOG_FIELDS` fields of `LOG_FORMAT`.
+fn commit_from_fields(f: &[&str]) -> Commit {
+    Commit {
+        sha: f[0].to_string(),
+        short_sha: f[1].to_string(),
+        author_name: f[2].to_string(),
+        author_email: f[3].to_string(),
+        timestamp: f[4].parse().unwrap_or(0),
+        parents: f[5].split_whitespace().map(str::to_string).collect(),
+        subject: f[6].to_string(),
+        // `%b` keeps the body's trailing newline
+        body: f[7].trim_end().to_string(),
+    }
+}
+
+fn parse_log(output: &str) -> Vec<Commit> {
+    let fields: Vec<&str> = output.split('\0').collect();
+    fields
+        .chunks_exact(LOG_FIELDS)
+        .map(commit_from_fields)
+        .collect()
+}
+
+/// False for a freshly initialised repository whose HEAD is unborn.
+fn has_commits(path: &str) -> Result<bool, GitError> {
+    let output = git_output(path, &["rev-parse", "--verify", "--quiet", "HEAD"])?;
+    if output.status.success() {
+        Ok(true)
+    } else if output.stderr.is_empty() {
+        // `--quiet` only silences the unborn-HEAD case
+        Ok(false)
+    } else {
+        Err(GitError::from_stderr(&output.stderr))
+    }
+}
+
+/// The full SHA of the commit `rev` names, or `None` if it names nothing or
+/// something other than a commit.
+fn resolve_commit(path: &str, rev: &str) -> Result<Option<String>, GitError> {
+    // `^{commit}` also rejects names of trees and blobs
+    let commit = format!("{rev}^{{commit}}");
+    let output = git_output(path, &["rev-parse", "--verify", "--quiet", &commit])?;
+    if output.status.success() {
+        return Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ));
+    }
+    match GitError::from_stderr(&output.stderr) {
+        GitError::NotARepository => Err(GitError::NotARepository),
+        _ => Ok(None),
+    }
+}
+
+/// Commit history from HEAD, newest first. `skip` and `limit` page through
+/// it so the UI can load more on scroll. A repo with no commits yet has an
+/// empty history rather than an error.
+#[tauri::command]
+pub fn git_log(path: String, limit: u32, skip: u32) -> Result<Vec<Commit>, GitError> {
+    if !has_commits(&path)? {
+        return Ok(Vec::new());
+    }
+
+    let limit = format!("--max-count={limit}");
+    let skip = format!("--skip={skip}");
+    let output = run_git(&path, &["log", "-z", LOG_FORMAT, &limit, &skip])?;
+    Ok(parse_log(&output))
+}
+
+/// The commit HEAD points at, or `None` in a repository without commits.
+/// Cheaper than `git_log` with a limit of 1.
+#[tauri::command]
+pub fn git_head_commit(path: String) -> Result<Option<Commit>, GitError> {
+    if !has_commits(&path)? {
+        return Ok(None);
+    }
+    let output = run_git(&path, &["show", "-s", "-z", LOG_FORMAT, "HEAD", "--"])?;
+    Ok(parse_log(&output).into_iter().next())
+}
+
+/// Up to `limit` commits with their parents, for drawing a commit graph.
+/// Only HEAD's history unless `all_refs`, which takes in every branch,
+/// remote-tracking branch and tag.
+#[tauri::command]
+pub fn git_log_graph(path: String, limit: u32, all_refs: bool) -> Result<GraphData, GitError> {
+    // `--all` copes with an unborn HEAD by itself
+    if !all_refs && !has_commits(&path)? {
+        return Ok(GraphData {
+            commits: Vec::new(),
+            refs: Vec::new(),
+        });
+    }
+
+    let limit = format!("--max-count={limit}");
+    let mut args = vec!["log", "-z", "--date-order", LOG_FORMAT, &limit];
+    if all_refs {
+        args.push("--all");
+    }
+    let commits = parse_log(&run_git(&path, &args)?);
+
+    let output = run_git(
+        &path,
+        &[
+            "for-each-ref",
+            "--format=%(refname)%00%(objectname)%00%(*objectname)%00%(symref)",
+            "refs/heads",
+            "refs/remotes",
+            "refs/tags",
+        ],
+    )?;
+    let mut refs: Vec<GraphRef> = output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\0');
+            let refname = fields.next()?;
+            let sha = fields.next()?;
+            let peeled = fields.next()?;
+            // Skip `origin/HEAD` and the like, which only alias a branch
+            if !fields.next()?.is_empty() {
+                return None;
+            }
+            let (name, kind) = if let Some(name) = refname.strip_prefix("refs/heads/") {
+                (name, RefKind::Branch)
+            } else if let Some(name) = refname.strip_prefix("refs/remotes/") {
+                (name, RefKind::RemoteBranch)
+            } else {
+                (refname.strip_prefix("refs/tags/")?, RefKind::Tag)
+            };
+            Some(GraphRef {
+                name: name.to_string(),
+                sha: if peeled.is_empty() { sha } else { peeled }.to_string(),
+                kind,
+            })
+        })
+        .collect();
+    if let Ok(head) = run_git(&path, &["rev-parse", "--verify", "--quiet", "HEAD"]) {
+        refs.insert(
+            0,
+            GraphRef {
+                name: "HEAD".to_string(),
+                sha: head.trim().to_string(),
+                kind: RefKind::Head,
+            },
+        );
+    }
+    let shas: HashSet<&str> = commits.iter().map(|c| c.sha.as_str()).collect();
+    refs.retain(|r| shas.contains(r.sha.as_str()));
+
+    Ok(GraphData { commits, refs })
+}
+
+/// The full SHA of whatever `rev` names: a ref, a tag, a SHA prefix or an
+/// expression like `HEAD~2` or `main@{yesterday}`. Annotated tags resolve
+/// to the tag object; see `git_object_type`.
+#[tauri::command]
+pub fn git_resolve_ref(path: String, rev: String) -> Result<String, GitError> {
+    validate_rev(&rev)?;
+    let output = git_output(&path, &["rev-parse", "--verify", "--quiet", &rev])?;
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).trim().to_string());
+    }
+    match GitError::from_stderr(&output.stderr) {
+        GitError::NotARepository => Err(GitError::NotARepository),
+        _ => Err(GitError::UnknownRevision { rev }),
+    }
+}
+
+/// What kind of object `rev` names.
+#[tauri::command]
+pub fn git_object_type(path: String, rev: String) -> Result<ObjectType, GitError> {
+    let sha = git_resolve_ref(path.clone(), rev)?;
+    match run_git(&path, &["cat-file", "-t", &sha])?.trim() {
+        "commit" => Ok(ObjectType::Commit),
+        "tree" => Ok(ObjectType::Tree),
+        "blob" => Ok(ObjectType::Blob),
+        "tag" => Ok(ObjectType::Tag),
+        other => Err(GitError::CommandFailed {
+            stderr: format!("unexpected object type '{other}'"),
+        }),
+    }
+}
+
+/// Commits that touched `file`, newest first, following it back through
+/// renames. Each carries the file's path in that commit, which differs from
+/// `file` before a rename.
+#[tauri::command]
+pub fn git_file_history(
+    path: String,
+    file: String,
+    limit: u32,
+) -> Result<Vec<FileRevision>, GitError> {
+    if !has_commits(&path)? {
+        return Ok(Vec::new());
+    }
+
+    let limit = format!("--max-count={limit}");
+    let output = run_git(
+        &path,
+        &[
+            "log",
+            "--follow",
+            "--name-only",
+            "-z",
+            FILE_LOG_FORMAT,
+            &limit,
+            "--",
+            &file,
+        ],
+    )?;
+
+    let revisions = output
+        .split('\x1e')
+        .filter_map(|record| {
+            let fields: Vec<&str> = record.splitn(LOG_FIELDS + 1, '\0').collect();
+            if fields.len() <= LOG_FIELDS {
+                return None;
+            }
+            // What's left is `\n<path>\0`
+            let name = fields[LOG_FIELDS].trim_start_matches('\n');
+            let name = name.split('\0').next().unwrap_or_default();
+            Some(FileRevision {
+                commit: commit_from_fields(&fields),
+                path: if name.is_empty() { &file } else { name }.to_string(),
+            })
+        })
+        .collect();
+    Ok(revisions)
+}
+
+/// How far the current branch is ahead of and behind its upstream. A branch
+/// without one (or a detached HEAD) reports `upstream: None` and zero counts.
+#[tauri::command]
+pub fn git_upstream_status(path: String) -> Result<UpstreamStatus, GitError> {
+    let output = git_output(
+        &path,
+        &[
+            "rev-parse",
+            "--abbrev-ref",
+            "--symbolic-full-name",
+            "@{upstream}",
+        ],
+    )?;
+    if !output.status.success() {
+        return match GitError::from_stderr(&output.stderr) {
+            GitError::NotARepository => Err(GitError::NotARepository),
+            _ => Ok(UpstreamStatus {
+                upstream: None,
+                ahead: 0,
+                behind: 0,
+            }),
+        };
+    }
+    let upstream = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    // Left side is the upstream, so the first count is commits we're behind
+    let counts = run_git(
+        &path,
+        &["rev-list", "--count", "--left-right", "@{upstream}...HEAD"],
+    )?;
+    let mut counts = counts.split_whitespace().map(|n| n.parse().unwrap_or(0));
+    let behind = counts.next().unwrap_or(0);
+    let ahead = counts.next().unwrap_or(0);
+
+    Ok(UpstreamStatus {
+        upstream: Some(upstream),
+        ahead,
+        behind,
+    })
+}
+
+/// The best common ancestor of `a` and `b`, or `None` if their histories
+/// share no commit.
+#[tauri::command]
+pub fn git_merge_base(path: String, a: String, b: String) -> Result<Option<String>, GitError> {
+    validate_rev(&a)?;
+    validate_rev(&b)?;
+    let output = git_output(&path, &["merge-base", &a, &b])?;
+    if output.status.success() {
+        return Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ));
+    }
+    // Unrelated histories exit with 1 and say nothing; bad refs explain
+    if output.status.code() == Some(1) && output.stderr.is_empty() {
+        return Ok(None);
+    }
+    Err(GitError::from_stderr(&output.stderr))
+}
+
+/// How far `a` and `b` have diverged: the commits each has that the other
+/// doesn't, and where they split.
+#[tauri::command]
+pub fn git_compare_refs(path: String, a: String, b: String) -> Result<RefComparison, GitError> {
+    validate_rev(&a)?;
+    validate_rev(&b)?;
+    let merge_base = git_merge_base(path.clone(), a.clone(), b.clone())?;
+
+    // Left side is `a`, so the first count is commits only `a` has
+    let range = format!("{a}...{b}");
+    let counts = run_git(&path, &["rev-list", "--count", "--left-right", &range])?;
+    let mut counts = counts.split_whitespace().map(|n| n.parse().unwrap_or(0));
+    let ahead = counts.next().unwrap_or(0);
+    let behind = counts.next().unwrap_or(0);
+
+    Ok(RefComparison {
+        ahead,
+        behind,
+        merge_base,
+    })
+}
+
+/// Stashes local changes, optionally including untracked files. Fails with
+/// `NothingToStash` rather than silently succeeding when there's nothing to
+/// save.
+#[tauri::command]
+pub fn git_stash(
+    path: String,
+    message: Option<String>,
+    include_untracked: bool,
+) -> Result<(), GitError> {
+    let mut args = vec!["stash", "push"];
+    if include_untracked {
+        args.push("--include-untracked");
+    }
+    if let Some(message) = &message {
+        args.extend(["-m", message]);
+    }
+
+    // Git exits 0 here and only says so on stdout
+    let output = run_git(&path, &args)?;
+    if output.contains("No local changes to save") {
+        return Err(GitError::NothingToStash);
+    }
+    Ok(())
+}
+
+/// Splits a stash reflog subject, `On <branch>: <message>` for stashes made
+/// with a message and `WIP on <branch>: <sha> <subject>` otherwise.
+fn parse_stash_subject(subject: &str) -> (Option<String>, String) {
+    let rest = subject
+        .strip_prefix("WIP on ")
+        .or_else(|| subject.strip_prefix("On "));
+    let Some((branch, message)) = rest.and_then(|r| r.split_once(": ")) else {
+        return (None, subject.to_string());
+    };
+    let branch = (branch != "(no branch)").then(|| branch.to_string());
+    (branch, message.to_string())
+}
+
+/// Lists stashes, most recent first.
+#[tauri::command]
+pub fn git_stash_list(path: String) -> Result<Vec<StashEntry>, GitError> {
+    let output = run_git(&path, &["stash", "list", "--format=%gd%x00%gs"])?;
+    let entries = output
+        .lines()
+        .filter_map(|line| {
+            let (selector, subject) = line.split_once('\0')?;
+            let index = selector
+                .strip_prefix("stash@{")?
+                .strip_suffix('}')?
+                .parse()
+                .ok()?;
+            let (branch, message) = parse_stash_subject(subject);
+            Some(StashEntry {
+                index,
+                message,
+                branch,
+            })
+        })
+        .collect();
+    Ok(entries)
+}
+
+/// Applies `stash@{index}` and drops it. If applying conflicts git keeps the
+/// stash and reports the error.
+#[tauri::command]
+pub fn git_stash_pop(path: String, index: u32) -> Result<(), GitError> {
+    let stash = format!("stash@{{{index}}}");
+    run_git(&path, &["stash", "pop", &stash]).map(|_| ())
+}
+
+/// Fetches from `remote`, returning git's summary of updated refs.
+#[tauri::command]
+pub async fn git_fetch(path: String, remote: String) -> Result<String, GitError> {
+    validate_remote(&remote)?;
+    run_blocking(move || {
+        run_git_network(
+            &path,
+            &["fetch", "--progress", &remote],
+            NETWORK_IDLE_TIMEOUT,
+        )
+    })
+    .await
+}
+
+/// Like `git_fetch`, but also deletes remote-tracking branches that no
+/// longer exist on `remote`. Local branches that tracked them are kept; see
+/// `git_stale_branches`.
+#[tauri::command]
+pub async fn git_fetch_prune(path: String, remote: String) -> Result<String, GitError> {
+    validate_remote(&remote)?;
+    run_blocking(move || {
+        run_git_network(
+            &path,
+            &["fetch", "--progress", "--prune", &remote],
+            NETWORK_IDLE_TIMEOUT,
+        )
+    })
+    .await
+}
+
+/// Local branches whose upstream is gone, typically deleted on the remote
+/// after a merge and then pruned. Branches without an upstream aren't
+/// included.
+#[tauri::command]
+pub fn git_stale_branches(path: String) -> Result<Vec<String>, GitError> {
+    let output = run_git(
+        &path,
+        &[
+            "for-each-ref",
+            "--format=%(refname:short)%00%(upstream:track)",
+            "refs/heads",
+        ],
+    )?;
+    Ok(output
+        .lines()
+        .filter_map(|line| line.split_once('\0'))
+        .filter(|(_, track)| *track == "[gone]")
+        .map(|(name, _)| name.to_string())
+        .collect())
+}
+
+/// Local branches whose history includes `sha`, and with `include_remotes`
+/// remote-tracking branches too, e.g. to show where a fix has landed.
+#[tauri::command]
+pub fn git_branches_containing(
+    path: String,
+    sha: String,
+    include_remotes: bool,
+) -> Result<Vec<String>, GitError> {
+    let Some(resolved) = resolve_commit(&path, &sha)? else {
+        return Err(GitError::UnknownCommit { rev: sha });
+    };
+
+    let contains = format!("--contains={resolved}");
+    let mut args = vec![
+        "for-each-ref",
+        &contains,
+        "--format=%(refname:short)%00%(symref)",
+        "refs/heads",
+    ];
+    if include_remotes {
+        args.push("refs/remotes");
+    }
+    let output = run_git(&path, &args)?;
+    Ok(output
+        .lines()
+        .filter_map(|line| line.split_once('\0'))
+        // Leave out aliases such as `origin/HEAD`
+        .filter(|(_, symref)| symref.is_empty())
+        .map(|(name, _)| name.to_string())
+        .collect())
+}
+
+/// Pulls `branch` from `remote` into the current branch.
+#[tauri::command]
+pub async fn git_pull(path: String, remote: String, branch: String) -> Result<String, GitError> {
+    validate_remote(&remote)?;
+    validate_rev(&branch)?;
+    run_blocking(move || {
+        run_git_network(
+            &path,
+            &["pull", "--progress", &remote, &branch],
+            NETWORK_IDLE_TIMEOUT,
+        )
+    })
+    .await
+}
+
+/// Pushes `branch` to `remote`, recording it as the upstream when
+/// `set_upstream` is set.
+#[tauri::command]
+pub async fn git_push(
+    path: String,
+    remote: String,
+    branch: String,
+    set_upstream: bool,
+) -> Result<String, GitError> {
+    validate_remote(&remote)?;
+    validate_rev(&branch)?;
+    run_blocking(move || {
+        let mut args = vec!["push", "--progress"];
+        if set_upstream {
+            args.push("--set-upstream");
+        }
+        args.extend([remote.as_str(), branch.as_str()]);
+        run_git_network(&path, &args, NETWORK_IDLE_TIMEOUT)
+    })
+    .await
+}
+
+/// Renames a local branch. Renaming the current branch works the same way
+/// and HEAD follows it. Fails with `BranchExists` if `new` is taken.
+#[tauri::command]
+pub fn git_rename_branch(path: String, old: String, new: String) -> Result<(), GitError> {
+    run_git(&path, &["branch", "-m", "--", &old, &new]).map(|_| ())
+}
+
+/// Local branches with their tip commit and upstream, plus remote-tracking
+/// branches with `include_remotes`. Locals come first, each group sorted by
+/// name; symbolic refs like `origin/HEAD` are left out.
+#[tauri::command]
+pub fn git_branches_detailed(
+    path: String,
+    include_remotes: bool,
+) -> Result<Vec<BranchInfo>, GitError> {
+    if let Some(branches) = with_libgit2(&path, |repo| libgit2_branches(repo, include_remotes)) {
+        return Ok(branches);
+    }
+    branches_from_refs(&path, include_remotes)
+}
+
+fn libgit2_branches(
+    repo: &git2::Repository,
+    include_remotes: bool,
+) -> Result<Vec<BranchInfo>, git2::Error> {
+    let mut kinds = vec![git2::BranchType::Local];
+    if include_remotes {
+        kinds.push(git2::BranchType::Remote);
+    }
+
+    let mut branches = Vec::new();
+    for kind in kinds {
+        let mut group = Vec::new();
+        for branch in repo.branches(Some(kind))? {
+            let (branch, _) = branch?;
+            let reference = branch.get();
+            let (Some(name), Some(refname)) = (branch.name()?, reference.name()) else {
+                continue;
+            };
+            if reference.kind() == Some(git2::ReferenceType::Symbolic) {
+                continue;
+            }
+            // Read from config, so an upstream whose ref is gone still shows
+            let upstream = match kind {
+                git2::BranchType::Local => repo.branch_upstream_name(refname).ok(),
+                git2::BranchType::Remote => None,
+            };
+            let upstream = upstream.and_then(|u| u.as_str().map(short_ref_name));
+            let commit = reference.peel_to_commit()?;
+            group.push(BranchInfo {
+                name: name.to_string(),
+                is_current: branch.is_head(),
+                is_remote: kind == git2::BranchType::Remote,
+                upstream,
+                last_commit_sha: commit.id().to_string(),
+                last_commit_subject: commit.summary().unwrap_or_default().to_string(),
+                last_commit_date: commit.committer().when().seconds(),
+            });
+        }
+        group.sort_by(|a, b| a.name.cmp(&b.name));
+        branches.append(&mut group);
+    }
+    Ok(branches)
+}
+
+/// `refs/heads/main` → `main`, `refs/remotes/origin/main` → `origin/main`.
+fn short_ref_name(refname: &str) -> String {
+    refname
+        .strip_prefix("refs/heads/")
+        .or_else(|| refname.strip_prefix("refs/remotes/"))
+        .unwrap_or(refname)
+        .to_string()
+}
+
+/// `git_branches_detailed` through the git CLI.
+fn branches_from_refs(path: &str, include_remotes: bool) -> Result<Vec<BranchInfo>, GitError> {
+    let mut args = vec![
+        "for-each-ref",
+        "--format=%(refname)%00%(refname:short)%00%(HEAD)%00%(upstream:short)%00%(symref)%00%(objectname)%00%(committerdate:unix)%00%(contents:subject)",
+        "refs/heads",
+    ];
+    if include_remotes {
+        args.push("refs/remotes");
+    }
+    let output = run_git(path, &args)?;
+
+    let branches = output
+        .lines()
+        .filter_map(|line| {
+            let f: Vec<&str> = line.splitn(8, '\0').collect();
+            if f.len() < 8 || !f[4].is_empty() {
+                return None;
+            }
+            Some(BranchInfo {
+                name: f[1].to_string(),
+                is_current: f[2] == "*",
+                is_remote: f[0].starts_with("refs/remotes/"),
+                upstream: (!f[3].is_empty()).then(|| f[3].to_string()),
+                last_commit_sha: f[5].to_string(),
+                last_commit_subject: f[7].to_string(),
+                last_commit_date: f[6].parse().unwrap_or(0),
+            })
+        })
+        .collect();
+    Ok(branches)
+}
+
+/// Configured remotes in the order git lists them. `git remote -v` prints a
+/// fetch and a push line per remote; they're merged into one entry.
+#[tauri::command]
+pub fn git_remotes(path: String) -> Result<Vec<Remote>, GitError> {
+    let output = run_git(&path, &["remote", "-v"])?;
+    let mut remotes: Vec<Remote> = Vec::new();
+
+    for line in output.lines() {
+        let Some((name, rest)) = line.split_once('\t') else {
+            continue;
+        };
+        let Some((url, kind)) = rest.rsplit_once(' ') else {
+            continue;
+        };
+
+        let index = match remotes.iter().position(|r| r.name == name) {
+            Some(index) => index,
+            None => {
+                remotes.push(Remote {
+                    name: name.to_string(),
+                    fetch_url: url.to_string(),
+                    push_url: url.to_string(),
+                });
+                remotes.len() - 1
+            }
+        };
+        match kind {
+            "(fetch)" => remotes[index].fetch_url = url.to_string(),
+            "(push)" => remotes[index].push_url = url.to_string(),
+            _ => {}
+        }
+    }
+
+    Ok(remotes)
+}
+
+#[tauri::command]
+pub fn git_add_remote(path: String, name: String, url: String) -> Result<(), GitError> {
+    run_git(&path, &["remote", "add", "--", &name, &url]).map(|_| ())
+}
+
+/// Renames a remote along with its remote-tracking branches and the
+/// upstream config of branches tracking it.
+#[tauri::command]
+pub fn git_rename_remote(path: String, old: String, new: String) -> Result<(), GitError> {
+    run_git(&path, &["remote", "rename", "--", &old, &new]).map(|_| ())
+}
+
+/// Removes a remote and its remote-tracking branches.
+#[tauri::command]
+pub fn git_remove_remote(path: String, name: String) -> Result<(), GitError> {
+    run_git(&path, &["remote", "remove", "--", &name]).map(|_| ())
+}
+
+/// Lists tags, highest version first (`v1.10` before `v1.9`).
+#[tauri::command]
+pub fn git_list_tags(path: String) -> Result<Vec<Tag>, GitError> {
+    // Fields are NUL-separated and records end in 0x1e, since annotations
+    // can span lines
+    let output = run_git(
+        &path,
+        &[
+            "for-each-ref",
+            "--sort=-version:refname",
+            "--format=%(refname:short)%00%(objecttype)%00%(objectname)%00%(*objectname)%00%(contents)%1e",
+            "refs/tags",
+        ],
+    )?;
+
+    let tags = output
+        .split('\x1e')
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').splitn(5, '\0');
+            let name = fields.next().filter(|n| !n.is_empty())?;
+            let is_annotated = fields.next()? == "tag";
+            let sha = fields.next()?;
+            let peeled = fields.next()?;
+            let contents = fields.next().unwrap_or_default().trim_end();
+
+            Some(Tag {
+                name: name.to_string(),
+                target_sha: if is_annotated { peeled } else { sha }.to_string(),
+                is_annotated,
+                message: is_annotated.then(|| contents.to_string()),
+            })
+        })
+        .collect();
+    Ok(tags)
+}
+
+/// Tags HEAD. With a `message` the tag is annotated and the message goes
+/// through stdin; without one it's a lightweight tag.
+#[tauri::command]
+pub fn git_create_tag(path: String, name: String, message: Option<String>) -> Result<(), GitError> {
+    match message {
+        Some(message) => {
+            run_git_with_stdin(&path, &["tag", "-a", "-F", "-", "--", &name], &message)
+        }
+        None => run_git(&path, &["tag", "--", &name]),
+    }
+    .map(|_| ())
+}
+
+/// Parses `git blame --porcelain`. Git only prints a commit's author and
+/// time the first time that commit appears, so later lines from the same
+/// commit look the metadata up by SHA.
+fn parse_blame(output: &str) -> Vec<BlameLine> {
+    let mut authors: HashMap<String, (String, i64)> = HashMap::new();
+    let mut lines = Vec::new();
+    let mut sha = String::new();
+    let mut line_number = 0;
+
+    for line in output.lines() {
+        if let Some(content) = line.strip_prefix('\t') {
+            let (author, timestamp) = authors.get(&sha).cloned().unwrap_or_default();
+            lines.push(BlameLine {
+                line_number,
+                uncommitted: sha.bytes().all(|b| b == b'0'),
+                sha: sha.clone(),
+                author,
+                timestamp,
+                content: content.to_string(),
+            });
+        } else if let Some(author) = line.strip_prefix("author ") {
+            authors.entry(sha.clone()).or_default().0 = author.to_string();
+        } else if let Some(time) = line.strip_prefix("author-time ") {
+            authors.entry(sha.clone()).or_default().1 = time.parse().unwrap_or(0);
+        } else {
+            // `<sha> <orig line> <final line> [<group size>]` starts a line
+            let mut parts = line.split(' ');
+            let (Some(first), Some(_), Some(final_line)) =
+                (parts.next(), parts.next(), parts.next())
+            else {
+                continue;
+            };
+            if first.len() == 40 && first.bytes().all(|b| b.is_ascii_hexdigit()) {
+                sha = first.to_string();
+                line_number = final_line.parse().unwrap_or(0);
+            }
+        }
+    }
+
+    lines
+}
+
+/// Runs `git blame <args> -- <file>` with `options` applied.
+fn run_blame(
+    path: &str,
+    args: &[&str],
+    file: &str,
+    options: &BlameOptions,
+) -> Result<Vec<BlameLine>, GitError> {
+    let mut blame_args = vec!["blame"];
+    blame_args.extend(args);
+    if let Some(revs_file) = &options.ignore_revs_file {
+        if !Path::new(path).join(revs_file).is_file() {
+            return Err(GitError::FileNotFound {
+                file: revs_file.clone(),
+            });
+        }
+        blame_args.extend(["--ignore-revs-file", revs_file]);
+    }
+    for rev in &options.ignore_revs {
+        blame_args.extend(["--ignore-rev", rev]);
+    }
+    blame_args.extend(["--", file]);
+    let ignoring = options.ignore_revs_file.is_some() || !options.ignore_revs.is_empty();
+
+    match run_git(path, &blame_args) {
+        Err(GitError::CommandFailed { stderr })
+            if ignoring && stderr.contains("unknown option") =>
+        {
+            Err(GitError::GitTooOld {
+                feature: "ignoring revisions in blame".to_string(),
+            })
+        }
+        result => result.map(|output| parse_blame(&output)),
+    }
+}
+
+/// Line-by-line authorship of `file` as it is in the working tree.
+#[tauri::command]
+pub fn git_blame(
+    path: String,
+    file: String,
+    options: Option<BlameOptions>,
+) -> Result<Vec<BlameLine>, GitError> {
+    run_blame(&path, &["--porcelain"], &file, &options.unwrap_or_default())
+}
+
+/// Like `git_blame`, but only for lines `start_line..=end_line` (1-based),
+/// so a viewport can be blamed as it scrolls. Line numbers in the result are
+/// the file's own, not offsets into the range.
+#[tauri::command]
+pub fn git_blame_range(
+    path: String,
+    file: String,
+    start_line: u32,
+    end_line: u32,
+    options: Option<BlameOptions>,
+) -> Result<Vec<BlameLine>, GitError> {
+    let contents = fs::read(Path::new(&path).join(&file)).map_err(|e| match e.kind() {
+        ErrorKind::NotFound => GitError::FileNotFound { file: file.clone() },
+        _ => e.into(),
+    })?;
+    let mut line_count = contents.iter().filter(|&&b| b == b'\n').count();
+    if contents.last().is_some_and(|&b| b != b'\n') {
+        line_count += 1;
+    }
+
+    if start_line == 0 || start_line > end_line {
+        return Err(GitError::InvalidArgument {
+            message: format!("invalid line range {start_line}-{end_line}"),
+        });
+    }
+    if end_line as usize > line_count {
+        return Err(GitError::InvalidArgument {
+            message: format!("'{file}' has only {line_count} lines"),
+        });
+    }
+
+    let range = format!("{start_line},{end_line}");
+    run_blame(
+        &path,
+        &["-L", &range, "--line-porcelain"],
+        &file,
+        &options.unwrap_or_default(),
+    )
+}
+
+/// Top-level directory of the working tree containing `path`, which may be
+/// a file or a directory at any depth. `None` when it isn't inside a
+/// repository.
+#[tauri::command]
+pub fn git_repo_root(path: String) -> Result<Option<String>, GitError> {
+    let target = Path::new(&path);
+    if fs::symlink_metadata(target).is_err() {
+        return Err(GitError::FileNotFound { file: path });
+    }
+    // `-C` needs a directory
+    let dir = if target.is_dir() {
+        target
+    } else {
+        target.parent().unwrap_or(target)
+    };
+
+    match run_git(&dir.to_string_lossy(), &["rev-parse", "--show-toplevel"]) {
+        Ok(root) => Ok(Some(root.trim().to_string())),
+        Err(GitError::NotARepository) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Branch, HEAD, commit/branch/tag counts, dirtiness and upstream distance
+/// in one call. A repository without commits yet reports zeros.
+#[tauri::command]
+pub fn git_repo_summary(path: String) -> Result<RepoSummary, GitError> {
+    // The `# branch.*` headers carry HEAD, branch and upstream distance, and
+    // any other line is a change, so one status answers all but the counts
+    let status = run_git(
+        &path,
+        &[
+            "status",
+            "--porcelain=v2",
+            "--branch",
+            "--untracked-files=normal",
+        ],
+    )?;
+    let mut head_sha = None;
+    let mut branch = None;
+    let (mut ahead, mut behind) = (0, 0);
+    let mut dirty = false;
+    for line in status.lines() {
+        let Some(header) = line.strip_prefix("# branch.") else {
+            dirty |= !line.is_empty() && !line.starts_with('#');
+            continue;
+        };
+        let (key, value) = header.split_once(' ').unwrap_or((header, ""));
+        match key {
+            // `(initial)` before the first commit
+            "oid" if value != "(initial)" => head_sha = Some(value.to_string()),
+            "head" if value != "(detached)" => branch = Some(value.to_string()),
+            "ab" => {
+                let mut counts = value
+                    .split_whitespace()
+                    .map(|n| n.trim_start_matches(['+', '-']).parse().unwrap_or(0));
+                ahead = counts.next().unwrap_or(0);
+                behind = counts.next().unwrap_or(0);
+            }
+            _ => {}
+        }
+    }
+
+    let current_branch = match branch {
+        Some(name) => BranchRef::Named { name },
+        // Detached HEAD always has a commit; git's default abbreviation
+        None => BranchRef::Detached {
+            short_sha: head_sha.iter().flat_map(|s| s.chars()).take(7).collect(),
+        },
+    };
+    let total_commits = match head_sha {
+        Some(_) => run_git(&path, &["rev-list", "--count", "HEAD"])?
+            .trim()
+            .parse()
+            .unwrap_or(0),
+        None => 0,
+    };
+
+    // One listing covers both counts
+    let refs = run_git(
+        &path,
+        &[
+            "for-each-ref",
+            "--format=%(refname)",
+            "refs/heads",
+            "refs/tags",
+        ],
+    )?;
+    let branch_count = refs
+        .lines()
+        .filter(|r| r.starts_with("refs/heads/"))
+        .count() as u32;
+    let tag_count = refs.lines().filter(|r| r.starts_with("refs/tags/")).count() as u32;
+
+    Ok(RepoSummary {
+        current_branch,
+        head_sha,
+        total_commits,
+        branch_count,
+        tag_count,
+        dirty,
+        ahead,
+        behind,
+    })
+}
+
+/// Initialises a new repository at `path`, optionally with a custom initial
+/// branch name. Refuses to create a nested repository inside an existing one.
+#[tauri::command]
+pub fn git_init(path: String, initial_branch: Option<String>) -> Result<(), GitError> {
+    if git_output(&path, &["rev-parse", "--git-dir"])?
+        .status
+        .success()
+    {
+        return Err(GitError::AlreadyARepository);
+    }
+
+    let Some(branch) = initial_branch else {
+        return run_git(&path, &["init"]).map(|_| ());
+    };
+    match run_git(&path, &["init", "--initial-branch", &branch]) {
+        // `--initial-branch` needs git 2.28; before that, init and then
+        // point the unborn HEAD at the branch
+        Err(GitError::CommandFailed { stderr }) if stderr.contains("unknown option") => {
+            run_git(&path, &["init"])?;
+            let head = format!("refs/heads/{branch}");
+            run_git(&path, &["symbolic-ref", "HEAD", &head]).map(|_| ())
+        }
+        result => result.map(|_| ()),
+    }
+}
+
+/// Loose sanity check, not RFC 5322: one `@` with something on both sides
+/// and nothing git would store oddly.
+fn looks_like_email(email: &str) -> bool {
+    let Some((local, domain)) = email.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.is_empty()
+        && !domain.contains('@')
+        && !email.contains(|c: char| c.is_whitespace() || c == '<' || c == '>')
+}
+
+/// `key` as set in exactly one config file, or `None` if it isn't.
+fn config_value(path: &str, scope: ConfigScope, key: &str) -> Result<Option<String>, GitError> {
+    let flag = match scope {
+        ConfigScope::Local => "--local",
+        ConfigScope::Global => "--global",
+        ConfigScope::System => "--system",
+    };
+    let output = git_output(path, &["config", flag, "--get", key])?;
+    if output.status.success() {
+        return Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ));
+    }
+    // Exit status 1 just means the key isn't set there
+    if output.status.code() == Some(1) {
+        return Ok(None);
+    }
+    Err(GitError::from_stderr(&output.stderr))
+}
+
+/// `key` from the most specific config file that sets it.
+fn scoped_config(path: &str, key: &str) -> Result<Option<(String, ConfigScope)>, GitError> {
+    for scope in [ConfigScope::Local, ConfigScope::Global, ConfigScope::System] {
+        if let Some(value) = config_value(path, scope, key)? {
+            return Ok(Some((value, scope)));
+        }
+    }
+    Ok(None)
+}
+
+/// The configured `user.name` and `user.email`, each taken from the repo's
+/// config before the global one, the way git resolves them. Environment
+/// overrides such as `GIT_AUTHOR_NAME` aren't considered.
+#[tauri::command]
+pub fn git_get_user(path: String) -> Result<GitIdentity, GitError> {
+    let name = scoped_config(&path, "user.name")?;
+    let email = scoped_config(&path, "user.email")?;
+    let source = name.as_ref().or(email.as_ref()).map(|(_, scope)| *scope);
+    Ok(GitIdentity {
+        name: name.map(|(value, _)| value),
+        email: email.map(|(value, _)| value),
+        source,
+    })
+}
+
+/// Removes `user.name` and `user.email` from the repo's config or, with
+/// `global`, from the user's global config. Keys that aren't set are fine.
+#[tauri::command]
+pub fn git_unset_user(path: String, global: bool) -> Result<(), GitError> {
+    let scope = if global { "--global" } else { "--local" };
+    for key in ["user.name", "user.email"] {
+        let output = git_output(&path, &["config", scope, "--unset-all", key])?;
+        // Exit status 5 is git's "nothing to unset"
+        if !output.status.success() && output.status.code() != Some(5) {
+            return Err(GitError::from_stderr(&output.stderr));
+        }
+    }
+    Ok(())
+}
+
+/// Sets `user.name` and `user.email`, in the repo's own config or, with
+/// `global`, in the user's global config.
+#[tauri::command]
+pub fn git_set_user(
+    path: String,
+    name: String,
+    email: String,
+    global: bool,
+) -> Result<(), GitError> {
+    let name = name.trim();
+    let email = email.trim();
+    if name.is_empty() {
+        return Err(GitError::InvalidArgument {
+            message: "Name must not be empty".to_string(),
+        });
+    }
+    if !looks_like_email(email) {
+        return Err(GitError::InvalidArgument {
+            message: format!("'{email}' is not a valid email address"),
+        });
+    }
+
+    let scope = if global { "--global" } else { "--local" };
+    run_git(&path, &["config", scope, "user.name", name])?;
+    run_git(&path, &["config", scope, "user.email", email])?;
+    Ok(())
+}
+
+/// Throws away local changes to `file` (or a directory), restoring index and
+/// working copy to HEAD.
+///
+/// Anything HEAD doesn't have (untracked or only just staged) can only be
+/// discarded by deleting it, so that requires `delete_untracked`. Files that
+/// exist in HEAD are only ever restored, never deleted.
+#[tauri::command]
+pub fn git_discard_changes(
+    path: String,
+    file: String,
+    delete_untracked: bool,
+) -> Result<(), GitError> {
+    let in_head =
+        has_commits(&path)? && !run_git(&path, &["ls-tree", "HEAD", "--", &file])?.is_empty();
+    if in_head {
+        return match run_git(
+            &path,
+            &[
+                "restore",
+                "--source=HEAD",
+                "--staged",
+                "--worktree",
+                "--",
+                &file,
+            ],
+        ) {
+            Err(GitError::CommandFailed { stderr }) if stderr.contains("is not a git command") => {
+                run_git(&path, &["checkout", "HEAD", "--", &file]).map(|_| ())
+            }
+            result => result.map(|_| ()),
+        };
+    }
+
+    let target = Path::new(&path).join(&file);
+    if fs::symlink_metadata(&target).is_err() {
+        return Err(GitError::FileNotFound { file });
+    }
+    if !delete_untracked {
+        return Err(GitError::InvalidArgument {
+            message: format!("'{file}' is untracked; discarding it would delete it"),
+        });
+    }
+
+    // Unstage anything newly added first so only untracked paths get deleted
+    if !run_git(&path, &["ls-files", "--", &file])?.is_empty() {
+        run_git(&path, &["rm", "-r", "--cached", "--quiet", "--", &file])?;
+    }
+    let removed = if target.is_dir() {
+        fs::remove_dir_all(&target)
+    } else {
+        fs::remove_file(&target)
+    };
+    Ok(removed?)
+}
+
+/// Throws away every local change under `path`: the index is reset and
+/// tracked files are restored to HEAD. Files that were only just staged are
+/// unstaged but kept.
+///
+/// Untracked files and directories (including those just-unstaged ones) are
+/// only removed with `include_untracked`, like `git clean -fd`. They go to
+/// the OS trash, or with `permanent` are deleted outright; where there is no
+/// trash this stops with `TrashFailed` rather than deleting anyway. Ignored
+/// files and nested repositories are left alone.
+#[tauri::command]
+pub fn git_discard_all(
+    path: String,
+    include_untracked: bool,
+    permanent: bool,
+) -> Result<(), GitError> {
+    if has_commits(&path)? {
+        run_git(&path, &["reset", "--quiet", "--", "."])?;
+        match run_git(&path, &["restore", "--worktree", "--", "."]) {
+            Err(GitError::CommandFailed { stderr }) if stderr.contains("is not a git command") => {
+                run_git(&path, &["checkout", "--", "."])?;
+            }
+            result => {
+                result?;
+            }
+        }
+    } else {
+        // Nothing to restore to; `rm --cached` unstages everything
+        run_git(
+            &path,
+            &[
+                "rm",
+                "-r",
+                "--cached",
+                "--quiet",
+                "--ignore-unmatch",
+                "--",
+                ".",
+            ],
+        )?;
+    }
+    if !include_untracked {
+        return Ok(());
+    }
+
+    // `--directory` lists a wholly untracked directory once, as `dir/`
+    let output = run_git(
+        &path,
+        &[
+            "ls-files",
+            "-z",
+            "--others",
+            "--exclude-standard",
+            "--directory",
+        ],
+    )?;
+    for relative in output.split('\0').filter(|p| !p.is_empty()) {
+        let target = Path::new(&path).join(relative);
+        if target.join(".git").exists() {
+            continue;
+        }
+        if !permanent {
+            trash::delete(&target).map_err(|e| GitError::TrashFailed {
+                file: relative.to_string(),
+                message: e.to_string(),
+            })?;
+        } else if target.is_dir() {
+            fs::remove_dir_all(&target)?;
+        } else {
+            fs::remove_file(&target)?;
+        }
+    }
+    Ok(())
+}
+
+/// The ignored files and directories under `path`, largest first, e.g. to
+/// show that `target/` holds 3 GB before offering to clean it. A directory
+/// git ignores as a whole is one entry with the size of everything in it.
+#[tauri::command]
+pub fn git_ignored_size(path: String) -> Result<Vec<IgnoredEntry>, GitError> {
+    // `matching` lists what the ignore rules name, rather than also every
+    // directory that merely holds nothing but ignored files
+    let output = run_git(
+        &path,
+        &[
+            "status",
+            "--porcelain=v1",
+            "-z",
+            "--ignored=matching",
+            "--",
+            ".",
+        ],
+    )?;
+    // Porcelain paths are relative to the top level
+    let prefix = run_git(&path, &["rev-parse", "--show-prefix"])?;
+    let prefix = prefix.trim_end_matches('\n');
+    let ignored = output
+        .split('\0')
+        .filter_map(|record| record.strip_prefix("!! "))
+        .map(|p| p.strip_prefix(prefix).unwrap_or(p));
+
+    let mut entries = Vec::new();
+    for relative in ignored {
+        let size = match tree_size(&Path::new(&path).join(relative)) {
+            Ok(size) => size,
+            // Removed since git listed it
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        entries.push(IgnoredEntry {
+            path: relative.trim_end_matches('/').to_string(),
+            is_dir: relative.ends_with('/'),
+            size,
+        });
+    }
+    entries.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    Ok(entries)
+}
+
+/// Contents of `file` at `ref_name`, or the staged version when `ref_name`
+/// is empty. Fails with `FileNotFound` if the file isn't in that ref or the
+/// index, and with `InvalidArgument` for content that isn't UTF-8 text.
+#[tauri::command]
+pub fn git_file_at_ref(path: String, ref_name: String, file: String) -> Result<String, GitError> {
+    let listed = if ref_name.is_empty() {
+        run_git(&path, &["ls-files", "--", &file])?
+    } else {
+        validate_rev(&ref_name)?;
+        run_git(&path, &["ls-tree", &ref_name, "--", &file])?
+    };
+    if listed.is_empty() {
+        return Err(GitError::FileNotFound { file });
+    }
+
+    // `./` makes the path relative to `path` rather than the repo root
+    let object = format!("{ref_name}:./{file}");
+    let output = run_git_bytes(&path, &["show", &object])?;
+    String::from_utf8(output).map_err(|_| GitError::InvalidArgument {
+        message: format!("'{file}' is not UTF-8 text"),
+    })
+}
+
+/// Replaces `file` in both the index and the working copy with its version
+/// at `ref_name`, e.g. to take one file from another branch. Any staged or
+/// unstaged changes to it are overwritten without a prompt, and the restored
+/// version is left staged.
+#[tauri::command]
+pub fn git_checkout_file(path: String, ref_name: String, file: String) -> Result<(), GitError> {
+    validate_rev(&ref_name)?;
+    if run_git(&path, &["ls-tree", &ref_name, "--", &file])?.is_empty() {
+        return Err(GitError::FileNotFound { file });
+    }
+    run_git(&path, &["checkout", &ref_name, "--", &file]).map(|_| ())
+}
+
+/// Parses `git diff-tree -z --name-status` into files with no line counts.
+/// Renames and copies carry a similarity score (`R100`) and two paths.
+fn parse_name_status_z(output: &str) -> Vec<ChangedFile> {
+    let mut fields = output.split('\0').filter(|f| !f.is_empty());
+    let mut files = Vec::new();
+
+    while let Some(status) = fields.next() {
+        let status: String = status.chars().take(1).collect();
+        let (orig_path, path) = if status == "R" || status == "C" {
+            let orig = fields.next();
+            (orig.map(str::to_string), fields.next())
+        } else {
+            (None, fields.next())
+        };
+        let Some(path) = path else {
+            break;
+        };
+        files.push(ChangedFile {
+            path: path.to_string(),
+            orig_path,
+            status,
+            additions: None,
+            deletions: None,
+        });
+    }
+
+    files
+}
+
+/// Fills in line counts from `git diff-tree -z --numstat`. Binary files
+/// report `-` for both counts, which stays `None`. A rename's record has an
+/// empty path followed by the old and new paths as separate fields.
+fn apply_numstat_z(files: &mut [ChangedFile], output: &str) {
+    let mut fields = output.split('\0');
+    while let Some(record) = fields.next() {
+        let mut parts = record.splitn(3, '\t');
+        let (Some(additions), Some(deletions), Some(path)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+        let path = if path.is_empty() {
+            fields.next();
+            fields.next().unwrap_or_default()
+        } else {
+            path
+        };
+        if let Some(file) = files.iter_mut().find(|f| f.path == path) {
+            file.additions = additions.parse().ok();
+            file.deletions = deletions.parse().ok();
+        }
+    }
+}
+
+/// Full details for one commit: its metadata plus every file it changed
+/// relative to its first parent, or to the empty tree for a root commit.
+#[tauri::command]
+pub fn git_show(path: String, sha: String) -> Result<CommitDetail, GitError> {
+    validate_rev(&sha)?;
+    let output = run_git(&path, &["show", "-s", "-z", LOG_FORMAT, &sha, "--"])?;
+    let Some(commit) = parse_log(&output).into_iter().next() else {
+        return Err(GitError::CommandFailed {
+            stderr: format!("'{sha}' is not a commit"),
+        });
+    };
+
+    let diff_args = |format: &'static str| -> Vec<&str> {
+        let mut args = vec!["diff-tree", "-r", "-z", "-M", "--no-commit-id", format];
+        match commit.parents.first() {
+            Some(parent) => args.push(parent),
+            None => args.push("--root"),
+        }
+        args.push(&commit.sha);
+        args
+    };
+    let mut files = parse_name_status_z(&run_git(&path, &diff_args("--name-status"))?);
+    apply_numstat_z(&mut files, &run_git(&path, &diff_args("--numstat"))?);
+
+    Ok(CommitDetail { commit, files })
+}
+
+/// Paths with unresolved conflicts in the index. Empty when there are none.
+fn conflicted_files(path: &str) -> Result<Vec<String>, GitError> {
+    let output = run_git(path, &["diff", "--name-only", "--diff-filter=U", "-z"])?;
+    Ok(output
+        .split('\0')
+        .filter(|f| !f.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+fn head_sha(path: &str) -> Result<String, GitError> {
+    Ok(run_git(path, &["rev-parse", "HEAD"])?.trim().to_string())
+}
+
+/// Merges `branch` into the current branch, using git's default message for
+/// any merge commit. `no_ff` forces a merge commit even when a fast-forward
+/// would do. Conflicts are a result, not an error, so the UI can list them.
+#[tauri::command]
+pub fn git_merge(path: String, branch: String, no_ff: bool) -> Result<MergeResult, GitError> {
+    validate_rev(&branch)?;
+    let before = head_sha(&path)?;
+
+    let mut args = vec!["merge", "--no-edit"];
+    if no_ff {
+        args.push("--no-ff");
+    }
+    args.push(&branch);
+    let output = git_output(&path, &args)?;
+
+    if !output.status.success() {
+        let files = conflicted_files(&path)?;
+        if files.is_empty() {
+            return Err(GitError::from_stderr(&output.stderr));
+        }
+        return Ok(MergeResult::Conflicts { files });
+    }
+
+    let sha = head_sha(&path)?;
+    if sha == before {
+        return Ok(MergeResult::UpToDate { sha });
+    }
+    let parents = run_git(&path, &["rev-list", "--parents", "-n", "1", "HEAD"])?;
+    // `<sha> <parent>...`, so a merge commit has three or more fields
+    if parents.split_whitespace().count() > 2 {
+        Ok(MergeResult::Merged { sha })
+    } else {
+        Ok(MergeResult::FastForward { sha })
+    }
+}
+
+/// How far a stopped rebase has got, as (current step, total steps), from
+/// the state files git keeps for the merge and apply backends.
+fn rebase_progress(path: &str) -> Result<(u32, u32), GitError> {
+    let git_dir = run_git(path, &["rev-parse", "--absolute-git-dir"])?;
+    let git_dir = Path::new(git_dir.trim());
+    let read = |dir: &str, file: &str| {
+        fs::read_to_string(git_dir.join(dir).join(file))
+            .ok()
+            .and_then(|n| n.trim().parse().ok())
+    };
+    let progress = match (read("rebase-merge", "msgnum"), read("rebase-merge", "end")) {
+        (Some(step), Some(total)) => (step, total),
+        _ => (
+            read("rebase-apply", "next").unwrap_or(0),
+            read("rebase-apply", "last").unwrap_or(0),
+        ),
+    };
+    Ok(progress)
+}
+
+/// Replays the current branch's own commits on top of `onto`. Conflicts
+/// are a result, like in `git_merge`. Only the non-interactive form is
+/// supported; there's no way to drive the todo editor from here.
+#[tauri::command]
+pub fn git_rebase(path: String, onto: String, interactive: bool) -> Result<RebaseResult, GitError> {
+    if interactive {
+        return Err(GitError::InvalidArgument {
+            message: "interactive rebase is not supported".into(),
+        });
+    }
+    validate_rev(&onto)?;
+    let before = head_sha(&path)?;
+
+    let output = git_command(&path, &["rebase", &onto])
+        .env("GIT_EDITOR", "true")
+        .output()?;
+    if !output.status.success() {
+        let files = conflicted_files(&path)?;
+        if files.is_empty() {
+            return Err(GitError::from_stderr(&output.stderr));
+        }
+        let (step, total) = rebase_progress(&path)?;
+        return Ok(RebaseResult::Conflicts { step, total, files });
+    }
+
+    let sha = head_sha(&path)?;
+    if sha == before {
+        Ok(RebaseResult::UpToDate { sha })
+    } else {
+        Ok(RebaseResult::Completed { sha })
+    }
+}
+
+/// Runs `cherry-pick` or `revert` on a single commit. Conflicts are a
+/// result, like in `git_merge`.
+fn apply_commit(
+    path: &str,
+    command: &str,
+    sha: &str,
+    no_commit: bool,
+) -> Result<ApplyResult, GitError> {
+    validate_rev(sha)?;
+    let mut args = vec![command];
+    if no_commit {
+        args.push("--no-commit");
+    } else if command == "revert" {
+        args.push("--no-edit");
+    }
+    args.push(sha);
+    let output = git_output(path, &args)?;
+
+    if !output.status.success() {
+        let files = conflicted_files(path)?;
+        if files.is_empty() {
+            return Err(GitError::from_stderr(&output.stderr));
+        }
+        return Ok(ApplyResult::Conflicts { files });
+    }
+    if no_commit {
+        return Ok(ApplyResult::Staged);
+    }
+    Ok(ApplyResult::Committed {
+        sha: head_sha(path)?,
+    })
+}
+
+/// Applies the change introduced by `sha` on top of HEAD. With `no_commit`
+/// it is only staged.
+#[tauri::command]
+pub fn git_cherry_pick(
+    path: String,
+    sha: String,
+    no_commit: bool,
+) -> Result<ApplyResult, GitError> {
+    apply_commit(&path, "cherry-pick", &sha, no_commit)
+}
+
+/// Creates a commit undoing `sha`, with git's default message. With
+/// `no_commit` the inverse change is only staged.
+#[tauri::command]
+pub fn git_revert(path: String, sha: String, no_commit: bool) -> Result<ApplyResult, GitError> {
+    apply_commit(&path, "revert", &sha, no_commit)
+}
+
+/// Which operation, if any, is paused in the repository, judged by the state
+/// files git leaves in the (per-worktree) git dir.
+fn operation_in_progress(path: &str) -> Result<Option<Operation>, GitError> {
+    let git_dir = run_git(path, &["rev-parse", "--absolute-git-dir"])?;
+    let git_dir = Path::new(git_dir.trim());
+
+    // A rebase that stops on a picked commit can leave CHERRY_PICK_HEAD
+    // around too, so it's checked first
+    let markers = [
+        ("rebase-merge", Operation::Rebase),
+        ("rebase-apply", Operation::Rebase),
+        ("CHERRY_PICK_HEAD", Operation::CherryPick),
+        ("REVERT_HEAD", Operation::Revert),
+        ("MERGE_HEAD", Operation::Merge),
+    ];
+    Ok(markers
+        .into_iter()
+        .find(|(marker, _)| git_dir.join(marker).exists())
+        .map(|(_, operation)| operation))
+}
+
+/// Unresolved files plus the operation that produced them. Both are empty
+/// in a repository with nothing in progress.
+#[tauri::command]
+pub fn git_conflicts(path: String) -> Result<ConflictState, GitError> {
+    Ok(ConflictState {
+        files: conflicted_files(&path)?,
+        in_progress: operation_in_progress(&path)?,
+    })
+}
+
+/// Abandons the paused operation, putting the branch, index and working
+/// tree back to how they were before it started.
+#[tauri::command]
+pub fn git_operation_abort(path: String) -> Result<(), GitError> {
+    let operation = operation_in_progress(&path)?.ok_or(GitError::NoOperationInProgress)?;
+    run_git(&path, &[operation.command(), "--abort"]).map(|_| ())
+}
+
+/// Resumes the paused operation once its conflicts are resolved and staged,
+/// keeping git's prepared commit messages. Returns the state afterwards: a
+/// rebase can stop again on the next commit's conflicts.
+#[tauri::command]
+pub fn git_operation_continue(path: String) -> Result<ConflictState, GitError> {
+    let operation = operation_in_progress(&path)?.ok_or(GitError::NoOperationInProgress)?;
+    let files = conflicted_files(&path)?;
+    if !files.is_empty() {
+        return Err(GitError::UnresolvedConflicts { files });
+    }
+
+    // `true` as the editor accepts each message as prepared
+    let output = git_command(&path, &[operation.command(), "--continue"])
+        .env("GIT_EDITOR", "true")
+        .output()?;
+    if !output.status.success() && conflicted_files(&path)?.is_empty() {
+        return Err(GitError::from_stderr(&output.stderr));
+    }
+    git_conflicts(path)
+}
+
+/// Moves the current branch to `target` (a SHA or ref like `HEAD~1`). There
+/// is deliberately no default `mode`: `Hard` discards uncommitted work, so
+/// the caller always has to ask for it by name.
+#[tauri::command]
+pub fn git_reset(path: String, target: String, mode: ResetMode) -> Result<(), GitError> {
+    let flag = match mode {
+        ResetMode::Soft => "--soft",
+        ResetMode::Mixed => "--mixed",
+        ResetMode::Hard => "--hard",
+    };
+    validate_rev(&target)?;
+    run_git(&path, &["reset", "--quiet", flag, &target, "--"]).map(|_| ())
+}
+
+/// Parses a progress line like `remote: Counting objects:  50% (5/10)` or
+/// `Receiving objects:  42% (420/1000), 1.20 MiB | 1.00 MiB/s`.
+fn parse_clone_progress(line: &str) -> Option<CloneProgress> {
+    let line = line.strip_prefix("remote: ").unwrap_or(line);
+    let (phase, rest) = line.split_once(": ")?;
+    let (percent, _) = rest.trim_start().split_once('%')?;
+    Some(CloneProgress {
+        phase: phase.to_string(),
+        percent: percent.parse().ok()?,
+    })
+}
+
+/// Clones into `dest`, which when relative is taken from `base`.
+fn clone_with_progress(
+    url: &str,
+    dest: &str,
+    base: &Path,
+    channel: &Channel<CloneProgress>,
+) -> Result<String, GitError> {
+    // `dest` doesn't exist yet and git creates any missing parents, so run
+    // from the nearest directory that does. `dest` is made absolute first so
+    // it isn't resolved against that directory a second time.
+    let dest = base.join(dest);
+    let parent = dest
+        .ancestors()
+        .find(|p| p.is_dir())
+        .unwrap_or(Path::new("/"));
+    let mut child = network_command(
+        &parent.to_string_lossy(),
+        &["clone", "--progress", "--", url, &dest.to_string_lossy()],
+    )
+    .stdout(Stdio::null())
+    .stderr(Stdio::piped())
+    .spawn()?;
+    let Some(stderr) = child.stderr.take() else {
+        return Err(GitError::Io {
+            message: "git stderr was not captured".to_string(),
+        });
+    };
+
+    // Progress redraws itself with `\r`, so split on that as well as `\n`.
+    // Everything that isn't progress is kept in case git fails.
+    let mut messages: Vec<String> = Vec::new();
+    let mut last: Option<CloneProgress> = None;
+    let mut line: Vec<u8> = Vec::new();
+    for byte in BufReader::new(stderr).bytes() {
+        let byte = byte?;
+        if byte != b'\r' && byte != b'\n' {
+            line.push(byte);
+            continue;
+        }
+
+        let text = String::from_utf8_lossy(&line).into_owned();
+        line.clear();
+        match parse_clone_progress(&text) {
+            Some(progress) if last.as_ref() != Some(&progress) => {
+                // The channel only fails once the frontend has gone away
+                if channel.send(progress.clone()).is_err() {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(GitError::Cancelled);
+                }
+                last = Some(progress);
+            }
+            Some(_) => {}
+            None if !text.trim().is_empty() => messages.push(text),
+            None => {}
+        }
+    }
+
+    if !child.wait()?.success() {
+        return Err(GitError::from_stderr(messages.join("\n").as_bytes()));
+    }
+    Ok(fs::canonicalize(&dest)
+        .unwrap_or(dest)
+        .to_string_lossy()
+        .to_string())
+}
+
+/// Clones `url` into `dest`, streaming progress over `channel`, and returns
+/// the absolute destination path. If the frontend drops the channel the
+/// clone is killed.
+#[tauri::command]
+pub async fn git_clone(
+    url: String,
+    dest: String,
+    channel: Channel<CloneProgress>,
+) -> Result<String, GitError> {
+    run_blocking(move || {
+        let base = std::env::current_dir()?;
+        clone_with_progress(&url, &dest, &base, &channel)
+    })
+    .await
+}
+
+/// Every worktree of the repository, the main one first.
+#[tauri::command]
+pub fn git_worktree_list(path: String) -> Result<Vec<Worktree>, GitError> {
+    let output = run_git(&path, &["worktree", "list", "--porcelain"])?;
+
+    // Blank-line separated records of `key [value]` lines
+    let worktrees = output
+        .split("\n\n")
+        .filter(|record| !record.trim().is_empty())
+        .enumerate()
+        .map(|(index, record)| {
+            let mut worktree = Worktree {
+                path: String::new(),
+                branch: None,
+                head_sha: None,
+                is_main: index == 0,
+                is_bare: false,
+                is_detached: false,
+                is_locked: false,
+            };
+            for line in record.lines() {
+                let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+                match key {
+                    "worktree" => worktree.path = value.to_string(),
+                    "HEAD" => worktree.head_sha = Some(value.to_string()),
+                    "branch" => {
+                        let name = value.strip_prefix("refs/heads/").unwrap_or(value);
+                        worktree.branch = Some(name.to_string());
+                    }
+                    "bare" => worktree.is_bare = true,
+                    "detached" => worktree.is_detached = true,
+                    "locked" => worktree.is_locked = true,
+                    _ => {}
+                }
+            }
+            worktree
+        })
+        .collect();
+    Ok(worktrees)
+}
+
+/// Checks `branch` out into a new worktree at `new_path`. Git refuses a
+/// branch that's already checked out elsewhere.
+#[tauri::command]
+pub fn git_worktree_add(path: String, new_path: String, branch: String) -> Result<(), GitError> {
+    validate_rev(&branch)?;
+    run_git(&path, &["worktree", "add", "--", &new_path, &branch]).map(|_| ())
+}
+
+/// Submodule settings from `.gitmodules` at `top_level`, as name → (key →
+/// value) for the `path` and `branch` keys. Empty without a `.gitmodules`.
+fn gitmodules(top_level: &str) -> HashMap<String, HashMap<String, String>> {
+    let Ok(output) = git_output(
+        top_level,
+        &[
+            "config",
+            "--file",
+            ".gitmodules",
+            "-z",
+            "--get-regexp",
+            r"^submodule\..*\.(path|branch)$",
+        ],
+    ) else {
+        return HashMap::new();
+    };
+    let mut modules: HashMap<String, HashMap<String, String>> = HashMap::new();
+    // Each entry is `submodule.<name>.<key>\n<value>`; names may hold dots
+    for entry in String::from_utf8_lossy(&output.stdout).split('\0') {
+        let Some((key, value)) = entry.split_once('\n') else {
+            continue;
+        };
+        let Some((name, field)) = key
+            .strip_prefix("submodule.")
+            .and_then(|rest| rest.rsplit_once('.'))
+        else {
+            continue;
+        };
+        modules
+            .entry(name.to_string())
+            .or_default()
+            .insert(field.to_string(), value.to_string());
+    }
+    modules
+}
+
+/// The repository's submodules and whether each is checked out at the
+/// commit the superproject records.
+#[tauri::command]
+pub fn git_submodule_status(path: String) -> Result<Vec<Submodule>, GitError> {
+    // From the top level, so paths match `.gitmodules`
+    let top_level = run_git(&path, &["rev-parse", "--show-toplevel"])?;
+    let top_level = top_level.trim_end_matches('\n');
+    let modules = gitmodules(top_level);
+    let branches: HashMap<&str, &str> = modules
+        .values()
+        .filter_map(|m| Some((m.get("path")?.as_str(), m.get("branch")?.as_str())))
+        .collect();
+    let known_paths: Vec<&str> = modules
+        .values()
+        .filter_map(|m| m.get("path").map(String::as_str))
+        .collect();
+
+    let output = run_git(top_level, &["submodule", "status"])?;
+    let submodules = output
+        .lines()
+        .filter_map(|line| {
+            let mut chars = line.chars();
+            let state = match chars.next()? {
+                ' ' => SubmoduleState::UpToDate,
+                '+' => SubmoduleState::OutOfSync,
+                '-' => SubmoduleState::Uninitialized,
+                'U' => SubmoduleState::Conflicted,
+                _ => return None,
+            };
+            let (sha, rest) = chars.as_str().split_once(' ')?;
+            // `rest` is the path, then ` (<describe>)` for checked-out
+            // submodules; paths can contain spaces and parentheses
+            let path = known_paths
+                .iter()
+                .find(|p| {
+                    rest == **p || rest.strip_prefix(**p).is_some_and(|r| r.starts_with(" ("))
+                })
+                .copied()
+                .or_else(|| rest.rsplit_once(" (").map(|(p, _)| p))
+                .unwrap_or(rest);
+            Some(Submodule {
+                path: path.to_string(),
+                sha: sha.to_string(),
+                branch: branches.get(path).map(|b| b.to_string()),
+                state,
+            })
+        })
+        .collect();
+    Ok(submodules)
+}
+
+/// Checks every submodule out at its recorded commit, fetching as needed.
+/// With `init`, submodules never cloned are cloned first; without it they
+/// are skipped. Returns git's output. Only a stalled transfer times out, so
+/// cloning a large submodule can take as long as it needs.
+#[tauri::command]
+pub async fn git_submodule_update(path: String, init: bool) -> Result<String, GitError> {
+    run_blocking(move || {
+        let mut args = vec!["submodule", "update", "--progress"];
+        if init {
+            args.push("--init");
+        }
+        run_git_network(&path, &args, NETWORK_IDLE_TIMEOUT)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::{Mutex, MutexGuard, Once};
+    use tempfile::TempDir;
+
+    /// Points git at an empty global config for the whole test run, so the
+    /// developer's own settings (signing, hooks, default branch) can't leak
+    /// into the fixtures.
+    fn isolate_config() {
+        static ONCE: Once = Once::new();
+        ONCE.call_once(|| {
+            let global =
+                std::env::temp_dir().join(format!("titan-gitconfig-{}", std::process::id()));
+            fs::write(&global, "").unwrap();
+            std::env::set_var("GIT_CONFIG_GLOBAL", global);
+            std::env::set_var("GIT_CONFIG_NOSYSTEM", "1");
+        });
+    }
+
+    /// Held by tests that read or write the shared global config, so one
+    /// test's global identity can't show up in another's lookup.
+    fn lock_global_config() -> MutexGuard<'static, ()> {
+        static GLOBAL_CONFIG: Mutex<()> = Mutex::new(());
+        GLOBAL_CONFIG.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Runs git in `dir` for fixture setup and returns its trimmed stdout.
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = git_command(&dir.to_string_lossy(), args).output().unwrap();
+        assert!(
+            output.status.success(),
+            "git {}: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    fn write(dir: &Path, file: &str, contents: &str) {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    /// Writes `file` and commits it with `message`, returning the new SHA.
+    fn commit_file(dir: &Path, file: &str, contents: &str, message: &str) -> String {
+        write(dir, file, contents);
+        git(dir, &["add", "--", file]);
+        git(dir, &["commit", "-q", "-m", message]);
+        git(dir, &["rev-parse", "HEAD"])
+    }
+
+    /// A repository on `main` with no commits yet.
+    fn empty_repo() -> TempDir {
+        isolate_config();
+        let dir = tempfile::tempdir().unwrap();
+        git(dir.path(), &["init", "-q", "-b", "main"]);
+        git(dir.path(), &["config", "user.name", "Test"]);
+        git(dir.path(), &["config", "user.email", "test@example.com"]);
+        dir
+    }
+
+    /// A repository on `main` whose one commit adds `a.txt`.
+    fn repo() -> TempDir {
+        let dir = empty_repo();
+        commit_file(dir.path(), "a.txt", "a\n", "initial");
+        dir
+    }
+
+    fn path_of(dir: &TempDir) -> String {
+        dir.path().to_string_lossy().to_string()
+    }
+
+    fn current_branch(dir: &TempDir) -> Option<String> {
+        match git_current_branch(path_of(dir)).unwrap() {
+            BranchRef::Named { name } => Some(name),
+            BranchRef::Detached { .. } => None,
+        }
+    }
+
+    #[test]
+    fn create_then_checkout_switches_the_current_branch() {
+        let dir = repo();
+        git_create_branch(path_of(&dir), "feature".into()).unwrap();
+        assert_eq!(current_branch(&dir).as_deref(), Some("main"));
+
+        git_checkout_branch(path_of(&dir), "feature".into()).unwrap();
+        assert_eq!(current_branch(&dir).as_deref(), Some("feature"));
+    }
+
+    #[test]
+    fn option_like_revs_never_reach_git() {
+        let dir = repo();
+        let out = dir.path().join("injected");
+        let rev = format!("--output={}", out.display());
+        let path = path_of(&dir);
+        let results = [
+            git_checkout_branch(path.clone(), rev.clone()).err(),
+            git_merge(path.clone(), rev.clone(), false).err(),
+            git_rebase(path.clone(), rev.clone(), false).err(),
+            git_reset(path.clone(), rev.clone(), ResetMode::Soft).err(),
+            git_show(path.clone(), rev.clone()).err(),
+            git_worktree_add(path.clone(), "wt".into(), rev.clone()).err(),
+        ];
+        for error in results {
+            assert!(matches!(error, Some(GitError::UnknownRevision { .. })));
+        }
+        assert!(!out.exists());
+        assert!(!PathBuf::from(&path).join("wt").exists());
+    }
+
+    #[test]
+    fn delete_branch_needs_force_for_unmerged_work() {
+        let dir = repo();
+        git(dir.path(), &["branch", "merged"]);
+        git_delete_branch(path_of(&dir), "merged".into(), false).unwrap();
+
+        git(dir.path(), &["checkout", "-q", "-b", "unmerged"]);
+        commit_file(dir.path(), "b.txt", "b\n", "unmerged work");
+        git(dir.path(), &["checkout", "-q", "main"]);
+        let error = git_delete_branch(path_of(&dir), "unmerged".into(), false).unwrap_err();
+        assert!(matches!(error, GitError::CommandFailed { .. }));
+        git_delete_branch(path_of(&dir), "unmerged".into(), true).unwrap();
+
+        let branches = git(dir.path(), &["branch", "--format=%(refname:short)"]);
+        assert_eq!(branches, "main");
+        let error = git_delete_branch(path_of(&dir), "main".into(), true).unwrap_err();
+        assert!(matches!(error, GitError::BranchIsCurrent { .. }));
+    }
+
+    #[test]
+    fn commit_returns_the_new_head() {
+        let dir = repo();
+        let error = git_commit(path_of(&dir), "empty".into(), false, None).unwrap_err();
+        assert!(matches!(error, GitError::NothingToCommit));
+
+        write(dir.path(), "a.txt", "changed\n");
+        git(dir.path(), &["add", "a.txt"]);
+        let message = "-subject\n\nbody with \"quotes\"";
+        let sha = git_commit(path_of(&dir), message.into(), false, None).unwrap();
+        assert_eq!(sha, git(dir.path(), &["rev-parse", "HEAD"]));
+        assert_eq!(git(dir.path(), &["log", "-1", "--format=%B"]), message);
+    }
+
+    fn porcelain(dir: &TempDir) -> String {
+        run_git(&path_of(dir), &["status", "--porcelain"]).unwrap()
+    }
+
+    #[test]
+    fn stage_and_unstage_flip_the_index_column() {
+        let dir = repo();
+        write(dir.path(), "a.txt", "changed\n");
+        write(dir.path(), "-n.txt", "flag-like name\n");
+        assert_eq!(porcelain(&dir), " M a.txt\n?? -n.txt\n");
+
+        git_stage_file(path_of(&dir), "a.txt".into()).unwrap();
+        git_stage_file(path_of(&dir), "-n.txt".into()).unwrap();
+        assert_eq!(porcelain(&dir), "A  -n.txt\nM  a.txt\n");
+
+        git_unstage_file(path_of(&dir), "a.txt".into()).unwrap();
+        assert_eq!(porcelain(&dir), "A  -n.txt\n M a.txt\n");
+    }
+
+    #[test]
+    fn log_is_newest_first_with_parent_links() {
+        let dir = repo();
+        commit_file(dir.path(), "a.txt", "two\n", "second\n\nbody\nmore");
+        commit_file(dir.path(), "a.txt", "three\n", "third");
+
+        let log = git_log(path_of(&dir), 10, 0).unwrap();
+        let subjects: Vec<&str> = log.iter().map(|c| c.subject.as_str()).collect();
+        assert_eq!(subjects, ["third", "second", "initial"]);
+        assert_eq!(log[1].body, "body\nmore");
+        assert_eq!(log[0].parents, [log[1].sha.clone()]);
+        assert_eq!(log[1].parents, [log[2].sha.clone()]);
+        assert!(log[2].parents.is_empty());
+
+        let page = git_log(path_of(&dir), 1, 1).unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].sha, log[1].sha);
+    }
+
+    fn status_rows(files: &[FileStatus]) -> Vec<String> {
+        files
+            .iter()
+            .map(|f| {
+                let orig = f.orig_path.as_deref().unwrap_or("");
+                format!("{}{} {} {orig}", f.index_status, f.worktree_status, f.path)
+            })
+            .collect()
+    }
+
+    fn cli_status(dir: &TempDir) -> Vec<String> {
+        status_rows(&parse_porcelain_z(&porcelain_z(dir)))
+    }
+
+    fn porcelain_z(dir: &TempDir) -> String {
+        run_git(&path_of(dir), &["status", "--porcelain=v1", "-z"]).unwrap()
+    }
+
+    #[test]
+    fn libgit2_status_matches_the_cli() {
+        let dir = repo();
+        commit_file(dir.path(), "gone.txt", "x\n", "add gone");
+        commit_file(dir.path(), "old name.txt", "renamed\n", "add old");
+        commit_file(dir.path(), "both.txt", "base\n", "add both");
+        let path = path_of(&dir);
+        assert!(with_libgit2(&path, libgit2_status).unwrap().is_empty());
+
+        git(dir.path(), &["checkout", "-q", "-b", "side"]);
+        commit_file(dir.path(), "both.txt", "side\n", "side edit");
+        git(dir.path(), &["checkout", "-q", "main"]);
+        commit_file(dir.path(), "both.txt", "main\n", "main edit");
+        git_merge(path.clone(), "side".into(), false).unwrap();
+
+        write(dir.path(), "a.txt", "modified\n");
+        write(dir.path(), "staged.txt", "new\n");
+        git(dir.path(), &["add", "staged.txt"]);
+        write(dir.path(), "staged.txt", "new, then edited\n");
+        git(dir.path(), &["mv", "old name.txt", "new name.txt"]);
+        fs::remove_file(dir.path().join("gone.txt")).unwrap();
+        write(dir.path(), "untracked/deep/file.txt", "u\n");
+        write(dir.path(), "loose.txt", "u\n");
+
+        let expected = cli_status(&dir);
+        assert_eq!(expected.len(), 7, "{expected:?}");
+        let actual = with_libgit2(&path, libgit2_status).unwrap();
+        assert_eq!(status_rows(&actual), expected);
+        assert_eq!(status_rows(&git_status_files(path).unwrap()), expected);
+    }
+
+    #[test]
+    fn libgit2_branch_queries_match_the_cli() {
+        let dir = empty_repo();
+        let path = path_of(&dir);
+        let libgit2 = |path: &str| with_libgit2(path, libgit2_current_branch).unwrap();
+        assert!(matches!(libgit2(&path), BranchRef::Named { name } if name == "main"));
+
+        commit_file(
+            dir.path(),
+            "a.txt",
+            "a\n",
+            "initial\nwrapped subject\n\nbody",
+        );
+        let origin = tempfile::tempdir().unwrap();
+        git(origin.path(), &["clone", "-q", &path, "."]);
+        let clone = origin.path().to_string_lossy().to_string();
+        git(origin.path(), &["branch", "local-only"]);
+        git(
+            origin.path(),
+            &["branch", "--set-upstream-to=origin/main", "local-only"],
+        );
+        git(origin.path(), &["config", "branch.stale.remote", "origin"]);
+        git(
+            origin.path(),
+            &["config", "branch.stale.merge", "refs/heads/stale"],
+        );
+        git(origin.path(), &["branch", "stale"]);
+
+        let rows = |branches: Vec<BranchInfo>| -> Vec<String> {
+            branches
+                .into_iter()
+                .map(|b| {
+                    format!(
+                        "{} {} {} {:?} {} {} {}",
+                        b.name,
+                        b.is_current,
+                        b.is_remote,
+                        b.upstream,
+                        b.last_commit_sha,
+                        b.last_commit_subject,
+                        b.last_commit_date
+                    )
+                })
+                .collect()
+        };
+        for include_remotes in [false, true] {
+            let expected = rows(branches_from_refs(&clone, include_remotes).unwrap());
+            let actual = with_libgit2(&clone, |repo| libgit2_branches(repo, include_remotes));
+            assert_eq!(rows(actual.unwrap()), expected);
+        }
+
+        let sha = git(dir.path(), &["rev-parse", "HEAD"]);
+        git(dir.path(), &["checkout", "-q", &sha]);
+        let short_sha = git(dir.path(), &["rev-parse", "--short", "HEAD"]);
+        assert!(matches!(libgit2(&path), BranchRef::Detached { short_sha: s } if s == short_sha));
+    }
+
+    #[test]
+    fn checking_out_a_sha_reports_a_detached_head() {
+        let dir = repo();
+        let first = git(dir.path(), &["rev-parse", "HEAD"]);
+        commit_file(dir.path(), "a.txt", "two\n", "second");
+        git(dir.path(), &["checkout", "-q", &first]);
+
+        let short = git(dir.path(), &["rev-parse", "--short", "HEAD"]);
+        match git_current_branch(path_of(&dir)).unwrap() {
+            BranchRef::Detached { short_sha } => assert_eq!(short_sha, short),
+            BranchRef::Named { name } => panic!("expected a detached HEAD, got {name}"),
+        }
+    }
+
+    /// A clone of `origin` with its own identity, tracking `origin/main`.
+    fn clone_of(origin: &TempDir) -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        git(dir.path(), &["clone", "-q", &path_of(origin), "."]);
+        git(dir.path(), &["config", "user.name", "Test"]);
+        git(dir.path(), &["config", "user.email", "test@example.com"]);
+        dir
+    }
+
+    #[test]
+    fn upstream_status_counts_both_sides_of_a_divergence() {
+        let origin = repo();
+        let no_upstream = git_upstream_status(path_of(&origin)).unwrap();
+        assert_eq!(
+            (no_upstream.upstream, no_upstream.ahead, no_upstream.behind),
+            (None, 0, 0)
+        );
+
+        let clone = clone_of(&origin);
+        commit_file(clone.path(), "local.txt", "1\n", "local one");
+        commit_file(clone.path(), "local.txt", "2\n", "local two");
+        commit_file(origin.path(), "remote.txt", "r\n", "remote one");
+        git(clone.path(), &["fetch", "-q"]);
+
+        let status = git_upstream_status(path_of(&clone)).unwrap();
+        assert_eq!(status.upstream.as_deref(), Some("origin/main"));
+        assert_eq!((status.ahead, status.behind), (2, 1));
+    }
+
+    #[test]
+    fn stash_round_trip() {
+        let dir = repo();
+        let error = git_stash(path_of(&dir), None, false).unwrap_err();
+        assert!(matches!(error, GitError::NothingToStash));
+
+        write(dir.path(), "a.txt", "work in progress\n");
+        git_stash(path_of(&dir), Some("my work".into()), false).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("a.txt")).unwrap(), "a\n");
+
+        let list = git_stash_list(path_of(&dir)).unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].index, 0);
+        assert_eq!(list[0].message, "my work");
+        assert_eq!(list[0].branch.as_deref(), Some("main"));
+
+        git_stash_pop(path_of(&dir), 0).unwrap();
+        assert!(git_stash_list(path_of(&dir)).unwrap().is_empty());
+        let contents = fs::read_to_string(dir.path().join("a.txt")).unwrap();
+        assert_eq!(contents, "work in progress\n");
+    }
+
+    #[test]
+    fn stash_takes_untracked_files_only_when_asked() {
+        let dir = repo();
+        write(dir.path(), "new.txt", "untracked\n");
+        let error = git_stash(path_of(&dir), None, false).unwrap_err();
+        assert!(matches!(error, GitError::NothingToStash));
+
+        git_stash(path_of(&dir), None, true).unwrap();
+        assert!(!dir.path().join("new.txt").exists());
+        assert_eq!(git_stash_list(path_of(&dir)).unwrap().len(), 1);
+    }
+
+    /// `fetch --progress` from `origin` through an upload-pack that first
+    /// runs `prelude`, with a one second idle timeout.
+    #[cfg(unix)]
+    fn fetch_after(clone: &TempDir, prelude: &str) -> Result<String, GitError> {
+        let upload_pack = format!("--upload-pack={prelude}; git-upload-pack");
+        let args = ["fetch", "--progress", &upload_pack, "origin"];
+        run_git_network(&path_of(clone), &args, Duration::from_secs(1))
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn network_commands_time_out_only_when_idle() {
+        let origin = repo();
+        let clone = clone_of(&origin);
+        commit_file(origin.path(), "b.txt", "b\n", "second");
+
+        let stalled = fetch_after(&clone, "sleep 3").unwrap_err();
+        assert!(
+            matches!(stalled, GitError::Timeout { seconds: 1 }),
+            "{stalled}"
+        );
+
+        // Longer than the timeout overall, but never quiet for that long
+        let ticking = "for i in 1 2 3 4; do echo tick >&2; sleep 0.5; done";
+        let summary = fetch_after(&clone, ticking).unwrap();
+        assert!(summary.contains("-> origin/main"), "{summary}");
+        assert!(!summary.contains("objects:"), "{summary}");
+    }
+
+    #[test]
+    fn rename_branch_works_for_other_and_current_branches() {
+        let dir = repo();
+        git(dir.path(), &["branch", "topic"]);
+        git_rename_branch(path_of(&dir), "topic".into(), "renamed".into()).unwrap();
+        let branches = git(dir.path(), &["branch", "--format=%(refname:short)"]);
+        assert_eq!(branches, "main\nrenamed");
+        assert_eq!(current_branch(&dir).as_deref(), Some("main"));
+
+        git_rename_branch(path_of(&dir), "main".into(), "trunk".into()).unwrap();
+        assert_eq!(current_branch(&dir).as_deref(), Some("trunk"));
+
+        let error = git_rename_branch(path_of(&dir), "trunk".into(), "renamed".into()).unwrap_err();
+        assert!(matches!(error, GitError::BranchExists { branch } if branch == "renamed"));
+    }
+
+    #[test]
+    fn remotes_merge_fetch_and_push_urls() {
+        let dir = repo();
+        assert!(git_remotes(path_of(&dir)).unwrap().is_empty());
+        git_add_remote(
+            path_of(&dir),
+            "origin".into(),
+            "https://example.com/a.git".into(),
+        )
+        .unwrap();
+        git_add_remote(
+            path_of(&dir),
+            "fork".into(),
+            "https://example.com/b.git".into(),
+        )
+        .unwrap();
+        let push_url = "ssh://git@example.com/b.git";
+        git(
+            dir.path(),
+            &["remote", "set-url", "--push", "fork", push_url],
+        );
+
+        let remotes: Vec<(String, String, String)> = git_remotes(path_of(&dir))
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.name, r.fetch_url, r.push_url))
+            .collect();
+        assert_eq!(
+            remotes,
+            [
+                (
+                    "fork".to_string(),
+                    "https://example.com/b.git".to_string(),
+                    push_url.to_string()
+                ),
+                (
+                    "origin".to_string(),
+                    "https://example.com/a.git".to_string(),
+                    "https://example.com/a.git".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn tags_read_back_lightweight_and_annotated() {
+        let dir = repo();
+        let first = git(dir.path(), &["rev-parse", "HEAD"]);
+        git_create_tag(path_of(&dir), "v1.2".into(), None).unwrap();
+        let second = commit_file(dir.path(), "a.txt", "two\n", "second");
+        git_create_tag(path_of(&dir), "v1.10".into(), Some("Release 1.10".into())).unwrap();
+
+        let tags = git_list_tags(path_of(&dir)).unwrap();
+        let names: Vec<&str> = tags.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["v1.10", "v1.2"]);
+        assert_eq!(tags[0].target_sha, second);
+        assert!(tags[0].is_annotated);
+        assert_eq!(tags[0].message.as_deref(), Some("Release 1.10"));
+        assert_eq!(tags[1].target_sha, first);
+        assert!(!tags[1].is_annotated);
+        assert_eq!(tags[1].message, None);
+    }
+
+    /// Commits everything staged or changed as `author` (`Name <email>`).
+    fn commit_as(dir: &Path, author: &str, message: &str) -> String {
+        git(
+            dir,
+            &[
+                "commit",
+                "-q",
+                "-a",
+                &format!("--author={author}"),
+                "-m",
+                message,
+            ],
+        );
+        git(dir, &["rev-parse", "HEAD"])
+    }
+
+    fn blame_rows(lines: &[BlameLine]) -> Vec<(u32, &str, &str)> {
+        lines
+            .iter()
+            .map(|l| (l.line_number, l.author.as_str(), l.content.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn blame_attributes_lines_to_each_author() {
+        let dir = repo();
+        write(dir.path(), "a.txt", "one\ntwo\nthree\n");
+        let alice = commit_as(dir.path(), "Alice <alice@example.com>", "write lines");
+        write(dir.path(), "a.txt", "one\nTWO\nthree\n");
+        let bob = commit_as(dir.path(), "Bob <bob@example.com>", "shout");
+        write(dir.path(), "a.txt", "one\nTWO\nthree\nfour\n");
+
+        let lines = git_blame(path_of(&dir), "a.txt".into(), None).unwrap();
+        assert_eq!(
+            blame_rows(&lines),
+            [
+                (1, "Alice", "one"),
+                (2, "Bob", "TWO"),
+                (3, "Alice", "three"),
+                (4, "Not Committed Yet", "four"),
+            ]
+        );
+        let shas: Vec<&str> = lines.iter().map(|l| l.sha.as_str()).collect();
+        assert_eq!(shas[..3], [alice.as_str(), bob.as_str(), alice.as_str()]);
+        assert!(lines[3].uncommitted && !lines[0].uncommitted);
+    }
+
+    #[test]
+    fn init_makes_a_fresh_directory_a_repository() {
+        isolate_config();
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(git_repo_root(path_of(&dir)).unwrap(), None);
+
+        git_init(path_of(&dir), Some("trunk".into())).unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        let found = git_repo_root(path_of(&dir)).unwrap().map(PathBuf::from);
+        assert_eq!(found, Some(root));
+        assert_eq!(current_branch(&dir).as_deref(), Some("trunk"));
+
+        let nested = dir.path().join("sub");
+        fs::create_dir(&nested).unwrap();
+        let error = git_init(nested.to_string_lossy().to_string(), None).unwrap_err();
+        assert!(matches!(error, GitError::AlreadyARepository));
+    }
+
+    #[test]
+    fn set_user_configures_the_repository() {
+        let _global = lock_global_config();
+        let dir = repo();
+        git(dir.path(), &["config", "--unset", "user.name"]);
+        git(dir.path(), &["config", "--unset", "user.email"]);
+        let before = git_get_user(path_of(&dir)).unwrap();
+        assert_eq!(
+            (before.name, before.email, before.source),
+            (None, None, None)
+        );
+
+        let error = git_set_user(path_of(&dir), "Ada".into(), "not-an-email".into(), false);
+        assert!(matches!(error, Err(GitError::InvalidArgument { .. })));
+
+        git_set_user(path_of(&dir), "Ada".into(), "ada@example.com".into(), false).unwrap();
+        let after = git_get_user(path_of(&dir)).unwrap();
+        assert_eq!(after.name.as_deref(), Some("Ada"));
+        assert_eq!(after.email.as_deref(), Some("ada@example.com"));
+        assert_eq!(after.source, Some(ConfigScope::Local));
+    }
+
+    #[test]
+    fn local_identity_overrides_global_until_unset() {
+        let _global = lock_global_config();
+        let dir = repo();
+        git_set_user(
+            path_of(&dir),
+            "Global".into(),
+            "global@example.com".into(),
+            true,
+        )
+        .unwrap();
+        git_set_user(
+            path_of(&dir),
+            "Local".into(),
+            "local@example.com".into(),
+            false,
+        )
+        .unwrap();
+
+        let user = git_get_user(path_of(&dir)).unwrap();
+        assert_eq!(user.name.as_deref(), Some("Local"));
+        assert_eq!(user.email.as_deref(), Some("local@example.com"));
+        assert_eq!(user.source, Some(ConfigScope::Local));
+
+        git_unset_user(path_of(&dir), false).unwrap();
+        let user = git_get_user(path_of(&dir)).unwrap();
+        assert_eq!(user.name.as_deref(), Some("Global"));
+        assert_eq!(user.email.as_deref(), Some("global@example.com"));
+        assert_eq!(user.source, Some(ConfigScope::Global));
+
+        git_unset_user(path_of(&dir), true).unwrap();
+        // Unsetting keys that are already gone is fine
+        git_unset_user(path_of(&dir), true).unwrap();
+        let user = git_get_user(path_of(&dir)).unwrap();
+        assert_eq!((user.name, user.email, user.source), (None, None, None));
+    }
+
+    fn read(dir: &TempDir, file: &str) -> String {
+        fs::read_to_string(dir.path().join(file)).unwrap()
+    }
+
+    #[test]
+    fn discard_changes_restores_tracked_files() {
+        let dir = repo();
+        write(dir.path(), "a.txt", "staged edit\n");
+        git(dir.path(), &["add", "a.txt"]);
+        write(dir.path(), "a.txt", "worktree edit\n");
+
+        git_discard_changes(path_of(&dir), "a.txt".into(), false).unwrap();
+        assert_eq!(read(&dir, "a.txt"), "a\n");
+        assert_eq!(porcelain(&dir), "");
+
+        let error = git_discard_changes(path_of(&dir), "missing.txt".into(), true).unwrap_err();
+        assert!(matches!(error, GitError::FileNotFound { .. }));
+    }
+
+    #[test]
+    fn discard_changes_deletes_untracked_files_only_when_asked() {
+        let dir = repo();
+        write(dir.path(), "new.txt", "untracked\n");
+        let error = git_discard_changes(path_of(&dir), "new.txt".into(), false).unwrap_err();
+        assert!(matches!(error, GitError::InvalidArgument { .. }));
+        assert!(dir.path().join("new.txt").exists());
+
+        git(dir.path(), &["add", "new.txt"]);
+        git_discard_changes(path_of(&dir), "new.txt".into(), true).unwrap();
+        assert!(!dir.path().join("new.txt").exists());
+        assert_eq!(porcelain(&dir), "");
+        assert_eq!(read(&dir, "a.txt"), "a\n");
+    }
+
+    #[test]
+    fn show_lists_files_with_line_counts() {
+        let dir = repo();
+        let root = git(dir.path(), &["rev-parse", "HEAD"]);
+        write(dir.path(), "a.txt", "a\nb\nc\n");
+        write(dir.path(), "bin.dat", "\0\x01\x02");
+        write(
+            dir.path(),
+            "moved.txt",
+            "moved contents\nare long enough\nto be a rename\n",
+        );
+        git(dir.path(), &["add", "."]);
+        git(dir.path(), &["commit", "-q", "-m", "setup"]);
+        git(dir.path(), &["mv", "moved.txt", "renamed.txt"]);
+        write(dir.path(), "a.txt", "a\nc\nd\ne\n");
+        fs::remove_file(dir.path().join("bin.dat")).unwrap();
+        let sha = commit_as(dir.path(), "Test <test@example.com>", "known commit");
+
+        let detail = git_show(path_of(&dir), sha.clone()).unwrap();
+        assert_eq!(detail.commit.sha, sha);
+        assert_eq!(detail.commit.subject, "known commit");
+        let files: Vec<String> = detail
+            .files
+            .iter()
+            .map(|f| {
+                let counts = format!("{:?} {:?}", f.additions, f.deletions);
+                let orig = f.orig_path.as_deref().unwrap_or("-");
+                format!("{} {} {orig} {counts}", f.status, f.path)
+            })
+            .collect();
+        assert_eq!(
+            files,
+            [
+                "M a.txt - Some(2) Some(1)",
+                "D bin.dat - None None",
+                "R renamed.txt moved.txt Some(0) Some(0)",
+            ]
+        );
+
+        let initial = git_show(path_of(&dir), root).unwrap();
+        assert!(initial.commit.parents.is_empty());
+        let files: Vec<(&str, &str)> = initial
+            .files
+            .iter()
+            .map(|f| (f.status.as_str(), f.path.as_str()))
+            .collect();
+        assert_eq!(files, [("A", "a.txt")]);
+    }
+
+    /// Leaves `main` checked out with a `side` branch that edits the same
+    /// line of `a.txt`, so merging either way conflicts.
+    fn diverge_on_a_txt(dir: &TempDir) {
+        git(dir.path(), &["checkout", "-q", "-b", "side"]);
+        commit_file(dir.path(), "a.txt", "side\n", "side edit");
+        git(dir.path(), &["checkout", "-q", "main"]);
+        commit_file(dir.path(), "a.txt", "main\n", "main edit");
+    }
+
+    #[test]
+    fn merge_fast_forwards_when_it_can() {
+        let dir = repo();
+        git(dir.path(), &["checkout", "-q", "-b", "ahead"]);
+        let tip = commit_file(dir.path(), "b.txt", "b\n", "ahead");
+        git(dir.path(), &["checkout", "-q", "main"]);
+
+        let result = git_merge(path_of(&dir), "ahead".into(), false).unwrap();
+        assert!(matches!(result, MergeResult::FastForward { sha } if sha == tip));
+        let result = git_merge(path_of(&dir), "ahead".into(), false).unwrap();
+        assert!(matches!(result, MergeResult::UpToDate { .. }));
+    }
+
+    #[test]
+    fn merge_reports_conflicting_files() {
+        let dir = repo();
+        diverge_on_a_txt(&dir);
+        let result = git_merge(path_of(&dir), "side".into(), false).unwrap();
+        assert!(matches!(result, MergeResult::Conflicts { files } if files == ["a.txt"]));
+    }
+
+    #[test]
+    fn conflicts_lists_unmerged_files_and_the_operation() {
+        let dir = repo();
+        let clean = git_conflicts(path_of(&dir)).unwrap();
+        assert!(clean.files.is_empty() && clean.in_progress.is_none());
+
+        diverge_on_a_txt(&dir);
+        git_merge(path_of(&dir), "side".into(), false).unwrap();
+        let state = git_conflicts(path_of(&dir)).unwrap();
+        assert_eq!(state.files, ["a.txt"]);
+        assert!(state.in_progress == Some(Operation::Merge));
+    }
+
+    #[test]
+    fn reset_moves_head_and_keeps_or_drops_changes_by_mode() {
+        let cases = [
+            (ResetMode::Soft, "MM a.txt\n", "uncommitted\n"),
+            (ResetMode::Mixed, " M a.txt\n", "uncommitted\n"),
+            (ResetMode::Hard, "", "a\n"),
+        ];
+        for (mode, status, contents) in cases {
+            let dir = repo();
+            let first = git(dir.path(), &["rev-parse", "HEAD"]);
+            commit_file(dir.path(), "a.txt", "second\n", "second");
+            write(dir.path(), "a.txt", "uncommitted\n");
+
+            git_reset(path_of(&dir), "HEAD~1".into(), mode).unwrap();
+            assert_eq!(git(dir.path(), &["rev-parse", "HEAD"]), first);
+            assert_eq!(porcelain(&dir), status);
+            assert_eq!(read(&dir, "a.txt"), contents);
+        }
+    }
+
+    #[test]
+    fn clone_resolves_a_relative_destination_once() {
+        let source = repo();
+        let base = TempDir::new().unwrap();
+        let channel = Channel::new(|_| Ok(()));
+        let cloned = clone_with_progress(&path_of(&source), "nested/clone", base.path(), &channel);
+
+        let expected = fs::canonicalize(base.path()).unwrap().join("nested/clone");
+        assert_eq!(PathBuf::from(cloned.unwrap()), expected);
+        assert_eq!(fs::read_to_string(expected.join("a.txt")).unwrap(), "a\n");
+    }
+
+    #[test]
+    fn branches_detailed_lists_upstreams_and_last_commits() {
+        let origin = repo();
+        let clone = clone_of(&origin);
+        git(clone.path(), &["checkout", "-q", "-b", "feature"]);
+        let feature = commit_file(clone.path(), "b.txt", "b\n", "feature work");
+        git(clone.path(), &["checkout", "-q", "main"]);
+        let main = git(clone.path(), &["rev-parse", "HEAD"]);
+
+        let rows = |include_remotes| -> Vec<String> {
+            git_branches_detailed(path_of(&clone), include_remotes)
+                .unwrap()
+                .into_iter()
+                .map(|b| {
+                    format!(
+                        "{} {} {} {:?} {} {}",
+                        b.name,
+                        b.is_current,
+                        b.is_remote,
+                        b.upstream,
+                        b.last_commit_sha,
+                        b.last_commit_subject
+                    )
+                })
+                .collect()
+        };
+        let local = [
+            format!("feature false false None {feature} feature work"),
+            format!("main true false Some(\"origin/main\") {main} initial"),
+        ];
+        assert_eq!(rows(false), local);
+        let mut all = local.to_vec();
+        all.push(format!("origin/main false true None {main} initial"));
+        assert_eq!(rows(true), all);
+    }
+
+    #[test]
+    fn checkout_file_restores_one_file_from_another_ref() {
+        let dir = repo();
+        git(dir.path(), &["tag", "v1"]);
+        write(dir.path(), "b.txt", "b\n");
+        commit_file(dir.path(), "a.txt", "a changed\n", "change a");
+
+        git_checkout_file(path_of(&dir), "v1".into(), "a.txt".into()).unwrap();
+        assert_eq!(read(&dir, "a.txt"), "a\n");
+        // Staged as well, and nothing else is touched
+        assert_eq!(porcelain(&dir), "M  a.txt\n?? b.txt\n");
+
+        let err = git_checkout_file(path_of(&dir), "v1".into(), "b.txt".into()).unwrap_err();
+        assert!(matches!(err, GitError::FileNotFound { file } if file == "b.txt"));
+    }
+
+    #[test]
+    fn cherry_pick_applies_a_commit_from_another_branch() {
+        let dir = repo();
+        git(dir.path(), &["checkout", "-q", "-b", "side"]);
+        let picked = commit_file(dir.path(), "b.txt", "b\n", "add b");
+        git(dir.path(), &["checkout", "-q", "main"]);
+        commit_file(dir.path(), "c.txt", "c\n", "add c");
+
+        let result = git_cherry_pick(path_of(&dir), picked.clone(), false).unwrap();
+        let ApplyResult::Committed { sha } = result else {
+            panic!("cherry-pick didn't commit");
+        };
+        assert_eq!(sha, git(dir.path(), &["rev-parse", "HEAD"]));
+        assert_ne!(sha, picked);
+        assert_eq!(git(dir.path(), &["log", "-1", "--format=%s"]), "add b");
+        assert_eq!(read(&dir, "b.txt"), "b\n");
+        assert_eq!(porcelain(&dir), "");
+    }
+
+    #[test]
+    fn file_history_follows_a_rename() {
+        let dir = repo();
+        commit_file(dir.path(), "a.txt", "a\nmore\n", "extend a");
+        git(dir.path(), &["mv", "a.txt", "b.txt"]);
+        git(dir.path(), &["commit", "-q", "-m", "rename a to b"]);
+        commit_file(dir.path(), "b.txt", "a\nmore\nb\n", "extend b");
+        commit_file(dir.path(), "other.txt", "x\n", "unrelated");
+
+        let history = git_file_history(path_of(&dir), "b.txt".into(), 10).unwrap();
+        let rows: Vec<(&str, &str)> = history
+            .iter()
+            .map(|r| (r.commit.subject.as_str(), r.path.as_str()))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("extend b", "b.txt"),
+                ("rename a to b", "b.txt"),
+                ("extend a", "a.txt"),
+                ("initial", "a.txt"),
+            ]
+        );
+        assert_eq!(
+            git_file_history(path_of(&dir), "b.txt".into(), 2)
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn amend_message_rewrites_only_the_message() {
+        let dir = repo();
+        let before = git(dir.path(), &["rev-parse", "HEAD"]);
+        let tree = git(dir.path(), &["rev-parse", "HEAD^{tree}"]);
+        write(dir.path(), "a.txt", "staged\n");
+        git(dir.path(), &["add", "a.txt"]);
+
+        let after = git_amend_message(path_of(&dir), "reworded\n\nwith a body".into()).unwrap();
+        assert_ne!(after, before);
+        assert_eq!(after, git(dir.path(), &["rev-parse", "HEAD"]));
+        assert_eq!(
+            git(dir.path(), &["log", "-1", "--format=%B"]),
+            "reworded\n\nwith a body"
+        );
+        assert_eq!(git(dir.path(), &["rev-parse", "HEAD^{tree}"]), tree);
+        assert_eq!(porcelain(&dir), "M  a.txt\n");
+    }
+
+    #[test]
+    fn file_at_ref_reads_commits_and_the_index() {
+        let dir = repo();
+        write(dir.path(), "a.txt", "staged\n");
+        git(dir.path(), &["add", "a.txt"]);
+        write(dir.path(), "a.txt", "worktree\n");
+        write(dir.path(), "sub/b.txt", "b\n");
+        git(dir.path(), &["add", "sub/b.txt"]);
+
+        let at = |ref_name: &str, file: &str| {
+            git_file_at_ref(path_of(&dir), ref_name.into(), file.into())
+        };
+        assert_eq!(at("HEAD", "a.txt").unwrap(), "a\n");
+        assert_eq!(at("", "a.txt").unwrap(), "staged\n");
+        assert_eq!(at("", "sub/b.txt").unwrap(), "b\n");
+        assert!(matches!(
+            at("HEAD", "sub/b.txt"),
+            Err(GitError::FileNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn worktree_add_shows_up_in_the_list() {
+        let dir = repo();
+        git(dir.path(), &["branch", "feature"]);
+        let parent = tempfile::tempdir().unwrap();
+        let new_path = parent.path().join("feature-tree");
+        let new_path = new_path.to_string_lossy().to_string();
+
+        git_worktree_add(path_of(&dir), new_path.clone(), "feature".into()).unwrap();
+        assert_eq!(
+            fs::read_to_string(Path::new(&new_path).join("a.txt")).unwrap(),
+            "a\n"
+        );
+
+        let head = git(dir.path(), &["rev-parse", "HEAD"]);
+        let worktrees = git_worktree_list(path_of(&dir)).unwrap();
+        let rows: Vec<(PathBuf, Option<&str>, Option<&str>, bool)> = worktrees
+            .iter()
+            .map(|w| {
+                let path = fs::canonicalize(&w.path).unwrap();
+                (path, w.branch.as_deref(), w.head_sha.as_deref(), w.is_main)
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (
+                    fs::canonicalize(dir.path()).unwrap(),
+                    Some("main"),
+                    Some(&*head),
+                    true
+                ),
+                (
+                    fs::canonicalize(&new_path).unwrap(),
+                    Some("feature"),
+                    Some(&*head),
+                    false
+                ),
+            ]
+        );
+        assert!(worktrees
+            .iter()
+            .all(|w| !w.is_bare && !w.is_detached && !w.is_locked));
+    }
+
+    #[test]
+    fn aborting_a_conflicted_merge_restores_the_prior_state() {
+        let dir = repo();
+        diverge_on_a_txt(&dir);
+        write(dir.path(), "untracked.txt", "kept\n");
+        let head = git(dir.path(), &["rev-parse", "HEAD"]);
+        let err = git_operation_abort(path_of(&dir)).unwrap_err();
+        assert!(matches!(err, GitError::NoOperationInProgress));
+
+        let merged = git_merge(path_of(&dir), "side".into(), false).unwrap();
+        assert!(matches!(merged, MergeResult::Conflicts { .. }));
+        git_operation_abort(path_of(&dir)).unwrap();
+
+        assert_eq!(git(dir.path(), &["rev-parse", "HEAD"]), head);
+        assert_eq!(read(&dir, "a.txt"), "main\n");
+        assert_eq!(porcelain(&dir), "?? untracked.txt\n");
+        let state = git_conflicts(path_of(&dir)).unwrap();
+        assert!(state.files.is_empty() && state.in_progress.is_none());
+    }
+
+    #[test]
+    fn apply_patch_stages_and_unstages_a_single_hunk() {
+        let dir = repo();
+        let lines: Vec<String> = (1..=12).map(|i| format!("line {i}\n")).collect();
+        commit_file(dir.path(), "a.txt", &lines.concat(), "twelve lines");
+        let mut edited = lines.clone();
+        edited[0] = "first\n".into();
+        edited[11] = "last\n".into();
+        write(dir.path(), "a.txt", &edited.concat());
+
+        // The file header plus the first of the two hunks
+        let diff = run_git(&path_of(&dir), &["diff", "--", "a.txt"]).unwrap();
+        let second = diff.match_indices("\n@@").nth(1).unwrap().0;
+        let first_hunk = format!("{}\n", &diff[..second]);
+
+        git_apply_patch(path_of(&dir), first_hunk.clone(), true, false).unwrap();
+        let staged = run_git(&path_of(&dir), &["diff", "--cached", "-U0"]).unwrap();
+        assert!(
+            staged.contains("+first") && !staged.contains("+last"),
+            "{staged}"
+        );
+        let unstaged = run_git(&path_of(&dir), &["diff", "-U0"]).unwrap();
+        assert!(
+            unstaged.contains("+last") && !unstaged.contains("+first"),
+            "{unstaged}"
+        );
+
+        // Applying it again no longer fits the index, and changes nothing
+        assert!(git_apply_patch(path_of(&dir), first_hunk.clone(), true, false).is_err());
+        git_apply_patch(path_of(&dir), first_hunk, true, true).unwrap();
+        assert_eq!(porcelain(&dir), " M a.txt\n");
+        assert_eq!(read(&dir, "a.txt"), edited.concat());
+    }
+
+    #[test]
+    fn blame_range_keeps_the_file_line_numbers() {
+        let dir = repo();
+        write(dir.path(), "a.txt", "1\n2\n3\n4\n5\n6\n");
+        commit_as(dir.path(), "Alice <alice@example.com>", "six lines");
+        write(dir.path(), "a.txt", "1\n2\n3\nfour\n5\n6\n");
+        commit_as(dir.path(), "Bob <bob@example.com>", "spell out four");
+
+        let range = |start, end| git_blame_range(path_of(&dir), "a.txt".into(), start, end, None);
+        assert_eq!(
+            blame_rows(&range(3, 5).unwrap()),
+            [(3, "Alice", "3"), (4, "Bob", "four"), (5, "Alice", "5")]
+        );
+        assert_eq!(blame_rows(&range(6, 6).unwrap()), [(6, "Alice", "6")]);
+        assert!(matches!(range(5, 7), Err(GitError::InvalidArgument { .. })));
+        assert!(matches!(range(0, 2), Err(GitError::InvalidArgument { .. })));
+    }
+
+    #[test]
+    fn discard_all_removes_untracked_files_only_when_asked() {
+        let dir = repo();
+        write(dir.path(), ".gitignore", "*.log\n");
+        git(dir.path(), &["add", ".gitignore"]);
+        git(dir.path(), &["commit", "-q", "-m", "ignore logs"]);
+        write(dir.path(), "a.txt", "edited\n");
+        write(dir.path(), "new.txt", "untracked\n");
+        write(dir.path(), "new_dir/b.txt", "untracked\n");
+        write(dir.path(), "debug.log", "ignored\n");
+        let nested = dir.path().join("nested");
+        fs::create_dir(&nested).unwrap();
+        git(&nested, &["init", "-q"]);
+
+        git_discard_all(path_of(&dir), false, false).unwrap();
+        assert_eq!(read(&dir, "a.txt"), "a\n");
+        assert_eq!(porcelain(&dir), "?? nested/\n?? new.txt\n?? new_dir/\n");
+
+        git_discard_all(path_of(&dir), true, true).unwrap();
+        assert_eq!(porcelain(&dir), "?? nested/\n");
+        assert!(!dir.path().join("new_dir").exists());
+        assert!(dir.path().join("debug.log").exists());
+    }
+
+    #[test]
+    fn discard_all_never_falls_back_to_deleting_permanently() {
+        let dir = repo();
+        write(dir.path(), "new.txt", "untracked\n");
+        // Whether a trash is available here depends on the machine; either
+        // way the file must only be gone when it went to the trash
+        match git_discard_all(path_of(&dir), true, false) {
+            Ok(()) => assert!(!dir.path().join("new.txt").exists()),
+            Err(GitError::TrashFailed { file, .. }) => {
+                assert_eq!(file, "new.txt");
+                assert!(dir.path().join("new.txt").exists());
+            }
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    #[test]
+    fn is_dirty_for_clean_untracked_and_staged_trees() {
+        let dir = repo();
+        let dirty = |include_untracked| git_is_dirty(path_of(&dir), include_untracked).unwrap();
+        assert!(!dirty(false) && !dirty(true));
+
+        write(dir.path(), "new.txt", "untracked\n");
+        assert!(!dirty(false));
+        assert!(dirty(true));
+
+        write(dir.path(), "a.txt", "staged\n");
+        git(dir.path(), &["add", "a.txt"]);
+        assert!(dirty(false) && dirty(true));
+
+        let not_repo = tempfile::tempdir().unwrap();
+        let err = git_is_dirty(not_repo.path().to_string_lossy().to_string(), false);
+        assert!(matches!(err, Err(GitError::NotARepository)));
+    }
+
+    #[test]
+    fn merge_base_and_compare_find_where_two_branches_split() {
+        let dir = repo();
+        let fork = git(dir.path(), &["rev-parse", "HEAD"]);
+        diverge_on_a_txt(&dir);
+        commit_file(dir.path(), "b.txt", "b\n", "main again");
+
+        let base = git_merge_base(path_of(&dir), "main".into(), "side".into()).unwrap();
+        assert_eq!(base.as_deref(), Some(fork.as_str()));
+
+        let comparison = git_compare_refs(path_of(&dir), "main".into(), "side".into()).unwrap();
+        assert_eq!((comparison.ahead, comparison.behind), (2, 1));
+        assert_eq!(comparison.merge_base, Some(fork));
+    }
+
+    #[test]
+    fn merge_base_and_compare_refuse_option_like_refs() {
+        let dir = repo();
+        for rev in ["--output=/tmp/x", "-p"] {
+            let error = git_merge_base(path_of(&dir), rev.into(), "main".into()).unwrap_err();
+            assert!(matches!(error, GitError::UnknownRevision { .. }));
+            let result = git_compare_refs(path_of(&dir), "main".into(), rev.into());
+            assert!(matches!(result, Err(GitError::UnknownRevision { .. })));
+        }
+    }
+
+    #[test]
+    fn rebase_replays_a_divergent_branch_onto_main() {
+        let dir = repo();
+        git(dir.path(), &["checkout", "-q", "-b", "feature"]);
+        commit_file(dir.path(), "b.txt", "b\n", "feature work");
+        git(dir.path(), &["checkout", "-q", "main"]);
+        let main_tip = commit_file(dir.path(), "c.txt", "c\n", "main work");
+        git(dir.path(), &["checkout", "-q", "feature"]);
+
+        let result = git_rebase(path_of(&dir), "main".into(), false).unwrap();
+        let RebaseResult::Completed { sha } = result else {
+            panic!("expected a completed rebase");
+        };
+        assert_eq!(sha, git(dir.path(), &["rev-parse", "HEAD"]));
+        assert_eq!(git(dir.path(), &["rev-parse", "HEAD~1"]), main_tip);
+        assert_eq!(read(&dir, "b.txt"), "b\n");
+        assert_eq!(read(&dir, "c.txt"), "c\n");
+        assert_eq!(current_branch(&dir).as_deref(), Some("feature"));
+
+        let again = git_rebase(path_of(&dir), "main".into(), false).unwrap();
+        assert!(matches!(again, RebaseResult::UpToDate { sha: same } if same == sha));
+    }
+
+    #[test]
+    fn create_branch_from_a_sha_points_there_without_switching() {
+        let dir = repo();
+        let first = git(dir.path(), &["rev-parse", "HEAD"]);
+        commit_file(dir.path(), "b.txt", "b\n", "second");
+
+        git_create_branch_from(path_of(&dir), "old".into(), first.clone()).unwrap();
+        assert_eq!(git(dir.path(), &["rev-parse", "old"]), first);
+        assert_eq!(current_branch(&dir).as_deref(), Some("main"));
+
+        let missing = "0123456789abcdef0123456789abcdef01234567";
+        let error = git_create_branch_from(path_of(&dir), "nope".into(), missing.into());
+        assert!(matches!(error, Err(GitError::InvalidStartPoint { .. })));
+        assert!(git(dir.path(), &["branch", "--list", "nope"]).is_empty());
+    }
+
+    #[test]
+    fn repo_root_is_found_from_a_nested_directory_or_file() {
+        let dir = repo();
+        write(dir.path(), "src/deep/er/main.rs", "fn main() {}\n");
+        let root = fs::canonicalize(dir.path()).unwrap();
+
+        for nested in ["src/deep/er", "src/deep/er/main.rs"] {
+            let path = dir.path().join(nested).to_string_lossy().to_string();
+            let found = git_repo_root(path).unwrap().map(PathBuf::from);
+            assert_eq!(found, Some(root.clone()));
+        }
+
+        let missing = dir.path().join("src/missing").to_string_lossy().to_string();
+        let error = git_repo_root(missing);
+        assert!(matches!(error, Err(GitError::FileNotFound { .. })));
+    }
+
+    #[test]
+    fn repo_summary_of_a_repository_with_history() {
+        let origin = repo();
+        let dir = clone_of(&origin);
+        commit_file(dir.path(), "b.txt", "b\n", "local");
+        git(dir.path(), &["branch", "other"]);
+        git(dir.path(), &["tag", "v1"]);
+
+        let summary = git_repo_summary(path_of(&dir)).unwrap();
+        assert!(matches!(&summary.current_branch, BranchRef::Named { name } if name == "main"));
+        let head = git(dir.path(), &["rev-parse", "HEAD"]);
+        assert_eq!(summary.head_sha, Some(head.clone()));
+        assert_eq!(summary.total_commits, 2);
+        assert_eq!((summary.branch_count, summary.tag_count), (2, 1));
+        assert_eq!((summary.ahead, summary.behind), (1, 0));
+        assert!(!summary.dirty);
+
+        write(dir.path(), "untracked.txt", "u\n");
+        assert!(git_repo_summary(path_of(&dir)).unwrap().dirty);
+
+        git(dir.path(), &["checkout", "-q", "--detach"]);
+        let summary = git_repo_summary(path_of(&dir)).unwrap();
+        assert!(
+            matches!(&summary.current_branch, BranchRef::Detached { short_sha } if head.starts_with(short_sha.as_str()))
+        );
+    }
+
+    #[test]
+    fn repo_summary_of_an_empty_repository_is_zeros() {
+        let dir = empty_repo();
+
+        let summary = git_repo_summary(path_of(&dir)).unwrap();
+        assert!(matches!(&summary.current_branch, BranchRef::Named { name } if name == "main"));
+        assert_eq!(summary.head_sha, None);
+        assert_eq!(summary.total_commits, 0);
+        assert_eq!((summary.branch_count, summary.tag_count), (0, 0));
+        assert_eq!((summary.ahead, summary.behind), (0, 0));
+        assert!(!summary.dirty);
+    }
+
+    #[test]
+    fn diff_file_refs_between_two_tags() {
+        let dir = repo();
+        git(dir.path(), &["tag", "v1"]);
+        commit_file(dir.path(), "a.txt", "a\nmore\n", "grow a");
+        commit_file(dir.path(), "b.txt", "b\n", "add b");
+        git(dir.path(), &["tag", "v2"]);
+
+        let diff =
+            git_diff_file_refs(path_of(&dir), "a.txt".into(), "v1".into(), "v2".into()).unwrap();
+        assert!(diff.contains("+more"), "{diff}");
+        assert!(!diff.contains("b.txt"), "{diff}");
+
+        let whole = git_diff_file_refs(path_of(&dir), "".into(), "v1".into(), "v2".into()).unwrap();
+        assert!(
+            whole.contains("a.txt") && whole.contains("b.txt"),
+            "{whole}"
+        );
+
+        let missing = git_diff_file_refs(path_of(&dir), "c.txt".into(), "v1".into(), "v2".into());
+        assert!(matches!(missing, Err(GitError::FileNotFound { .. })));
+    }
+
+    #[test]
+    fn diff_file_refs_refuses_option_like_refs() {
+        let dir = repo();
+        let output = dir.path().join("injected.txt");
+        let rev = format!("--output={}", output.display());
+
+        let error = git_diff_file_refs(path_of(&dir), "".into(), rev.clone(), "HEAD".into());
+        assert!(matches!(error, Err(GitError::UnknownRevision { .. })));
+        let error = git_diff_file_refs(path_of(&dir), "a.txt".into(), "HEAD".into(), rev);
+        assert!(matches!(error, Err(GitError::UnknownRevision { .. })));
+        assert!(!output.exists());
+    }
+
+    #[test]
+    fn head_commit_describes_the_latest_commit() {
+        let dir = repo();
+        let first = git(dir.path(), &["rev-parse", "HEAD"]);
+        write(dir.path(), "b.txt", "b\n");
+        git(dir.path(), &["add", "b.txt"]);
+        git(
+            dir.path(),
+            &["commit", "-q", "-m", "add b", "-m", "with a body"],
+        );
+
+        let head = git_head_commit(path_of(&dir)).unwrap().unwrap();
+        assert_eq!(head.sha, git(dir.path(), &["rev-parse", "HEAD"]));
+        assert!(head.sha.starts_with(&head.short_sha));
+        assert_eq!(head.subject, "add b");
+        assert_eq!(head.body.trim(), "with a body");
+        assert_eq!(
+            (head.author_name.as_str(), head.author_email.as_str()),
+            ("Test", "test@example.com")
+        );
+        assert_eq!(head.parents, [first]);
+        assert!(head.timestamp > 0);
+    }
+
+    #[test]
+    fn head_commit_of_an_empty_repository_is_none() {
+        let dir = empty_repo();
+        assert!(git_head_commit(path_of(&dir)).unwrap().is_none());
+    }
+
+    #[test]
+    fn stale_branches_are_those_whose_upstream_is_gone() {
+        let origin = repo();
+        git(origin.path(), &["branch", "doomed"]);
+        git(origin.path(), &["branch", "kept"]);
+        let dir = clone_of(&origin);
+        for branch in ["doomed", "kept"] {
+            git(
+                dir.path(),
+                &["branch", "--track", branch, &format!("origin/{branch}")],
+            );
+        }
+        git(dir.path(), &["branch", "local-only"]);
+        assert!(git_stale_branches(path_of(&dir)).unwrap().is_empty());
+
+        git(origin.path(), &["branch", "-D", "doomed"]);
+        git(dir.path(), &["fetch", "-q", "--prune"]);
+
+        assert_eq!(git_stale_branches(path_of(&dir)).unwrap(), ["doomed"]);
+    }
+
+    #[test]
+    fn renaming_and_removing_a_remote_carries_its_branches() {
+        let origin = repo();
+        let dir = clone_of(&origin);
+        let names = |dir: &TempDir| -> Vec<String> {
+            git_remotes(path_of(dir))
+                .unwrap()
+                .into_iter()
+                .map(|r| r.name)
+                .collect()
+        };
+
+        git_rename_remote(path_of(&dir), "origin".into(), "upstream".into()).unwrap();
+        assert_eq!(names(&dir), ["upstream"]);
+        assert_eq!(
+            git(
+                dir.path(),
+                &["rev-parse", "--abbrev-ref", "main@{upstream}"]
+            ),
+            "upstream/main"
+        );
+        assert!(git_rename_remote(path_of(&dir), "origin".into(), "other".into()).is_err());
+
+        git_remove_remote(path_of(&dir), "upstream".into()).unwrap();
+        assert!(names(&dir).is_empty());
+        assert!(git(dir.path(), &["branch", "-r"]).is_empty());
+        assert!(git_remove_remote(path_of(&dir), "upstream".into()).is_err());
+    }
+
+    #[test]
+    fn log_graph_gives_a_merge_commit_both_parents() {
+        let dir = repo();
+        git(dir.path(), &["checkout", "-q", "-b", "topic"]);
+        let topic = commit_file(dir.path(), "b.txt", "b\n", "topic work");
+        git(dir.path(), &["checkout", "-q", "main"]);
+        let main = commit_file(dir.path(), "c.txt", "c\n", "main work");
+        git(
+            dir.path(),
+            &["merge", "-q", "--no-ff", "-m", "merge topic", "topic"],
+        );
+
+        let graph = git_log_graph(path_of(&dir), 10, false).unwrap();
+        assert_eq!(graph.commits.len(), 4);
+        let merge = &graph.commits[0];
+        assert_eq!(merge.subject, "merge topic");
+        assert_eq!(merge.parents, [main, topic.clone()]);
+        // Children always come before their parents
+        let position = |sha: &str| graph.commits.iter().position(|c| c.sha == sha).unwrap();
+        assert!(position(&merge.sha) < position(&topic));
+        assert!(graph
+            .refs
+            .iter()
+            .any(|r| r.name == "topic" && r.sha == topic));
+    }
+
+    #[test]
+    fn blame_can_look_through_a_reformat_commit() {
+        let dir = repo();
+        write(dir.path(), "a.txt", "if (x) {\n  call(x);\n}\n");
+        commit_as(dir.path(), "Alice <alice@example.com>", "write code");
+        write(dir.path(), "a.txt", "if (x) {\n    call(x);\n}\n");
+        let reformat = commit_as(dir.path(), "Bot <bot@example.com>", "reformat");
+
+        let plain = git_blame(path_of(&dir), "a.txt".into(), None).unwrap();
+        assert_eq!(blame_rows(&plain)[1], (2, "Bot", "    call(x);"));
+
+        let options = BlameOptions {
+            ignore_revs: vec![reformat.clone()],
+            ..Default::default()
+        };
+        let ignoring = git_blame(path_of(&dir), "a.txt".into(), Some(options)).unwrap();
+        assert!(blame_rows(&ignoring)
+            .iter()
+            .all(|(_, author, _)| *author == "Alice"));
+
+        write(
+            dir.path(),
+            ".git-blame-ignore-revs",
+            &format!("# formatting\n{reformat}\n"),
+        );
+        let options = BlameOptions {
+            ignore_revs_file: Some(".git-blame-ignore-revs".into()),
+            ..Default::default()
+        };
+        let from_file = git_blame(path_of(&dir), "a.txt".into(), Some(options)).unwrap();
+        assert_eq!(blame_rows(&from_file), blame_rows(&ignoring));
+
+        let missing = BlameOptions {
+            ignore_revs_file: Some("no-such-file".into()),
+            ..Default::default()
+        };
+        let error = git_blame(path_of(&dir), "a.txt".into(), Some(missing));
+        assert!(matches!(error, Err(GitError::FileNotFound { .. })));
+    }
+
+    #[test]
+    fn ignored_size_totals_an_ignored_directory() {
+        let dir = repo();
+        write(dir.path(), ".gitignore", "target/\n*.log\n");
+        git(dir.path(), &["add", ".gitignore"]);
+        git(dir.path(), &["commit", "-q", "-m", "ignore"]);
+        write(dir.path(), "target/debug/app", &"x".repeat(3000));
+        write(dir.path(), "target/notes.txt", &"y".repeat(500));
+        write(dir.path(), "sub/run.log", "12345");
+        write(dir.path(), "sub/kept.txt", "kept");
+
+        let rows: Vec<_> = git_ignored_size(path_of(&dir))
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.path, e.is_dir, e.size))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("target".to_string(), true, 3500),
+                ("sub/run.log".to_string(), false, 5),
+            ]
+        );
+
+        // From a subdirectory, paths are relative to it
+        let sub = dir.path().join("sub").to_string_lossy().to_string();
+        let rows: Vec<_> = git_ignored_size(sub)
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.path, e.size))
+            .collect();
+        assert_eq!(rows, [("run.log".to_string(), 5)]);
+    }
+
+    #[test]
+    fn status_grouped_lists_a_staged_and_modified_file_in_both_groups() {
+        let dir = repo();
+        write(dir.path(), "a.txt", "staged\n");
+        git(dir.path(), &["add", "a.txt"]);
+        write(dir.path(), "a.txt", "staged\nand more\n");
+        write(dir.path(), "new.txt", "new\n");
+
+        let groups = git_status_grouped(path_of(&dir)).unwrap();
+        let paths = |files: &[FileStatus]| -> Vec<String> {
+            files.iter().map(|f| f.path.clone()).collect()
+        };
+        assert_eq!(paths(&groups.staged), ["a.txt"]);
+        assert_eq!(paths(&groups.unstaged), ["a.txt"]);
+        assert_eq!(
+            (
+                groups.staged[0].index_status,
+                groups.unstaged[0].worktree_status
+            ),
+            ('M', 'M')
+        );
+        assert_eq!(groups.untracked, ["new.txt"]);
+        assert!(groups.conflicted.is_empty());
+    }
+
+    #[test]
+    fn branches_containing_a_commit_on_two_branches() {
+        let origin = repo();
+        let dir = clone_of(&origin);
+        git(dir.path(), &["checkout", "-q", "-b", "feature"]);
+        let shared = commit_file(dir.path(), "b.txt", "b\n", "shared");
+        git(dir.path(), &["branch", "also"]);
+        commit_file(dir.path(), "c.txt", "c\n", "feature only");
+        git(dir.path(), &["push", "-q", "origin", "feature"]);
+        git(dir.path(), &["fetch", "-q"]);
+
+        let local = git_branches_containing(path_of(&dir), shared.clone(), false).unwrap();
+        assert_eq!(local, ["also", "feature"]);
+        let all = git_branches_containing(path_of(&dir), shared[..8].into(), true).unwrap();
+        assert_eq!(all, ["also", "feature", "origin/feature"]);
+
+        let initial = git(dir.path(), &["rev-parse", "main"]);
+        let everywhere = git_branches_containing(path_of(&dir), initial, false).unwrap();
+        assert_eq!(everywhere, ["also", "feature", "main"]);
+
+        let missing = git_branches_containing(path_of(&dir), "nope".into(), false);
+        assert!(matches!(missing, Err(GitError::UnknownCommit { .. })));
+    }
+
+    #[test]
+    fn resolve_ref_and_object_type_for_head_and_its_parent() {
+        let dir = repo();
+        let first = git(dir.path(), &["rev-parse", "HEAD"]);
+        let second = commit_file(dir.path(), "b.txt", "b\n", "second");
+        git(dir.path(), &["tag", "-a", "v1", "-m", "release"]);
+
+        assert_eq!(
+            git_resolve_ref(path_of(&dir), "HEAD".into()).unwrap(),
+            second
+        );
+        assert_eq!(
+            git_resolve_ref(path_of(&dir), "HEAD~1".into()).unwrap(),
+            first
+        );
+
+        let kind = |rev: &str| git_object_type(path_of(&dir), rev.into()).unwrap();
+        assert!(kind("HEAD") == ObjectType::Commit);
+        assert!(kind("HEAD~1") == ObjectType::Commit);
+        assert!(kind("HEAD^{tree}") == ObjectType::Tree);
+        assert!(kind("HEAD:b.txt") == ObjectType::Blob);
+        assert!(kind("v1") == ObjectType::Tag);
+    }
+
+    #[test]
+    fn resolve_ref_and_object_type_reject_an_invalid_rev() {
+        let dir = repo();
+        for rev in ["HEAD~5", "no-such-branch", "--all"] {
+            let resolved = git_resolve_ref(path_of(&dir), rev.into());
+            assert!(
+                matches!(resolved, Err(GitError::UnknownRevision { .. })),
+                "{rev}"
+            );
+            let kind = git_object_type(path_of(&dir), rev.into());
+            assert!(
+                matches!(kind, Err(GitError::UnknownRevision { .. })),
+                "{rev}"
+            );
+        }
+    }
+
+    #[test]
+    fn submodule_update_init_clones_a_registered_submodule() {
+        let _global = lock_global_config();
+        let library = repo();
+        let dir = repo();
+        // Local clones of submodules are refused by default since git 2.38,
+        // and the nested clone only reads global config
+        git(
+            dir.path(),
+            &["config", "--global", "protocol.file.allow", "always"],
+        );
+        git(
+            dir.path(),
+            &["submodule", "add", "-q", &path_of(&library), "lib"],
+        );
+        git(dir.path(), &["commit", "-q", "-m", "add lib"]);
+        let fresh = tempfile::tempdir().unwrap();
+        git(fresh.path(), &["clone", "-q", &path_of(&dir), "."]);
+
+        let status = git_submodule_status(path_of(&fresh)).unwrap();
+        assert_eq!(status.len(), 1);
+        assert_eq!(status[0].state, SubmoduleState::Uninitialized);
+
+        let updated = tauri::async_runtime::block_on(git_submodule_update(path_of(&fresh), true));
+        git(
+            dir.path(),
+            &["config", "--global", "--unset", "protocol.file.allow"],
+        );
+        updated.unwrap();
+        assert_eq!(
+            fs::read_to_string(fresh.path().join("lib/a.txt")).unwrap(),
+            "a\n"
+        );
+        let status = git_submodule_status(path_of(&fresh)).unwrap();
+        assert_eq!(status[0].path, "lib");
+        assert_eq!(status[0].state, SubmoduleState::UpToDate);
+    }
+
+    #[test]
+    fn network_commands_refuse_option_like_remotes_and_branches() {
+        use tauri::async_runtime::block_on;
+        let origin = repo();
+        let clone = clone_of(&origin);
+        let marker = clone.path().join("ran");
+        let remote = format!("--upload-pack=touch {}; git-upload-pack", marker.display());
+        let refused = |result: Result<String, GitError>| {
+            matches!(
+                result,
+                Err(GitError::InvalidArgument { .. } | GitError::UnknownRevision { .. })
+            )
+        };
+
+        assert!(refused(block_on(git_fetch(
+            path_of(&clone),
+            remote.clone()
+        ))));
+        assert!(refused(block_on(git_fetch(path_of(&clone), String::new()))));
+        let pull = git_pull(path_of(&clone), remote.clone(), "main".into());
+        assert!(refused(block_on(pull)));
+        let pull = git_pull(path_of(&clone), "origin".into(), remote.clone());
+        assert!(refused(block_on(pull)));
+        let push = git_push(
+            path_of(&clone),
+            "--receive-pack=true".into(),
+            "main".into(),
+            false,
+        );
+        assert!(refused(block_on(push)));
+        let push = git_push(path_of(&clone), "origin".into(), "--force".into(), false);
+        assert!(refused(block_on(push)));
+        assert!(!marker.exists());
+
+        // A real remote still goes through
+        block_on(git_fetch(path_of(&clone), "origin".into())).unwrap();
+    }
+
+    #[test]
+    fn fetch_prune_refuses_an_option_like_remote() {
+        let origin = repo();
+        let clone = clone_of(&origin);
+        let marker = clone.path().join("ran");
+        let remote = format!("--upload-pack=touch {}; git-upload-pack", marker.display());
+
+        let result = tauri::async_runtime::block_on(git_fetch_prune(path_of(&clone), remote));
+        assert!(matches!(result, Err(GitError::InvalidArgument { .. })));
+        assert!(!marker.exists());
+    }
+
+    #[test]
+    fn cherry_pick_and_revert_refuse_option_like_shas() {
+        let dir = repo();
+        let head = git(dir.path(), &["rev-parse", "HEAD"]);
+        for sha in ["--strategy=ours", "-n", ""] {
+            let picked = git_cherry_pick(path_of(&dir), sha.into(), false);
+            assert!(
+                matches!(picked, Err(GitError::UnknownRevision { .. })),
+                "{sha}"
+            );
+            let reverted = git_revert(path_of(&dir), sha.into(), false);
+            assert!(
+                matches!(reverted, Err(GitError::UnknownRevision { .. })),
+                "{sha}"
+            );
+        }
+        assert_eq!(git(dir.path(), &["rev-parse", "HEAD"]), head);
+    }
+
+    #[test]
+    fn file_at_ref_refuses_an_option_like_ref() {
+        let dir = repo();
+        let output = dir.path().join("written.txt");
+        let rev = format!("--output={}", output.display());
+
+        let result = git_file_at_ref(path_of(&dir), rev, "a.txt".into());
+        assert!(matches!(result, Err(GitError::UnknownRevision { .. })));
+        assert!(!output.exists());
+        // The index is still read when no ref is given
+        assert_eq!(
+            git_file_at_ref(path_of(&dir), "".into(), "a.txt".into()).unwrap(),
+            "a\n"
+        );
+    }
+
+    #[test]
+    fn checkout_file_refuses_option_like_refs() {
+        let dir = repo();
+        write(dir.path(), "a.txt", "edited\n");
+
+        for rev in ["-f", "--orphan=x", ""] {
+            let result = git_checkout_file(path_of(&dir), rev.into(), "a.txt".into());
+            assert!(
+                matches!(result, Err(GitError::UnknownRevision { .. })),
+                "{rev}"
+            );
+        }
+        assert_eq!(read(&dir, "a.txt"), "edited\n");
+        assert_eq!(current_branch(&dir).as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn diff_of_an_untracked_file_shows_it_added() {
+        let dir = repo();
+        write(dir.path(), "new.txt", "one\ntwo\n");
+
+        let diff = git_diff(path_of(&dir), "new.txt".into(), false).unwrap();
+        assert!(diff.contains("new file mode"), "{diff}");
+        assert!(diff.contains("--- /dev/null\n+++ b/new.txt\n"), "{diff}");
+        assert!(diff.contains("+one\n+two\n"), "{diff}");
+        // Nothing is staged, so the index has no diff for it
+        assert_eq!(git_diff(path_of(&dir), "new.txt".into(), true).unwrap(), "");
+    }
+}
@@ -1,89 +1,147 @@
-use serde::Serialize;
-use std::fs;
-use std::path::Path;
+mod files;
+mod git;
+mod recent;
+mod scan;
+mod search;
+mod stats;
+mod system;
+mod terminal;
+mod watch;
+mod workspace;
 
-#[derive(Serialize)]
-pub struct DirEntry {
-    pub name: String,
-    pub path: String,
-    pub children: Vec<DirEntry>,
-}
-
-const SKIP_DIRS: &[&str] = &[
-    "node_modules",
-    "target",
-    "dist",
-    ".git",
-    ".svn",
-    ".hg",
-    "__pycache__",
-    ".next",
-    ".nuxt",
-    "build",
-];
-
-fn scan_recursive(dir: &Path, depth: u32, max_depth: u32) -> Vec<DirEntry> {
-    if depth >= max_depth {
-        return Vec::new();
-    }
-
-    let Ok(entries) = fs::read_dir(dir) else {
-        return Vec::new();
-    };
-
-    let mut result: Vec<DirEntry> = Vec::new();
-
-    for entry in entries.flatten() {
-        let Ok(file_type) = entry.file_type() else {
-            continue;
-        };
-        if !file_type.is_dir() {
-            continue;
-        }
-
-        let name = entry.file_name().to_string_lossy().to_string();
-
-        // Skip hidden directories and known non-project dirs
-        if name.starts_with('.') || SKIP_DIRS.contains(&name.as_str()) {
-            continue;
-        }
-
-        let path = entry.path();
-        let children = scan_recursive(&path, depth + 1, max_depth);
-
-        result.push(DirEntry {
-            name,
-            path: path.to_string_lossy().to_string(),
-            children,
-        });
-    }
-
-    result.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
-    result
-}
-
-#[tauri::command]
-fn scan_directory(root: String) -> DirEntry {
-    let path = Path::new(&root);
-    let name = path
-        .file_name()
-        .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or_else(|| root.clone());
-
-    let children = scan_recursive(path, 0, 4);
-
-    DirEntry {
-        name,
-        path: root,
-        children,
-    }
-}
+use tauri::Manager;
 
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_pty::init())
-        .invoke_handler(tauri::generate_handler![scan_directory])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .manage(recent::RecentProjects::default())
+        .manage(scan::ActiveScans::default())
+        .manage(scan::ScanCache::default())
+        .manage(watch::WatcherState::default())
+        .manage(watch::TailState::default())
+        .manage(terminal::TerminalManager::default())
+        .invoke_handler(tauri::generate_handler![
+            scan::scan_directory,
+            scan::scan_directories,
+            scan::scan_directory_streamed,
+            scan::scan_directory_flat,
+            scan::scan_directory_cancellable,
+            scan::scan_directory_async,
+            scan::cancel_scan,
+            scan::invalidate_scan_cache,
+            scan::list_children,
+            scan::detect_project_type,
+            search::search_files,
+            search::search_content,
+            stats::project_stats,
+            files::file_metadata,
+            files::set_executable,
+            files::read_file_text,
+            files::is_binary_file,
+            files::write_file_text,
+            files::create_file,
+            files::create_dir,
+            files::rename_entry,
+            files::move_entry,
+            files::move_entry_with_progress,
+            files::copy_entry,
+            files::copy_entry_with_progress,
+            files::resolve_path,
+            files::canonical_path,
+            files::delete_entry,
+            system::reveal_in_file_manager,
+            system::check_tools,
+            watch::watch_directory,
+            watch::unwatch_directory,
+            watch::tail_file,
+            watch::untail_file,
+            workspace::workspace_config_get,
+            workspace::workspace_config_set,
+            recent::add_recent_project,
+            recent::list_recent_projects,
+            recent::remove_recent_project,
+            terminal::spawn_terminal,
+            terminal::open_terminal_in,
+            terminal::write_terminal,
+            terminal::resize_terminal,
+            terminal::kill_terminal,
+            git::git_current_branch,
+            git::git_checkout_branch,
+            git::git_create_branch,
+            git::git_create_branch_from,
+            git::git_delete_branch,
+            git::git_rename_branch,
+            git::git_branches_detailed,
+            git::git_status_files,
+            git::git_status_grouped,
+            git::git_is_dirty,
+            git::git_commit,
+            git::git_amend_message,
+            git::git_stage_file,
+            git::git_unstage_file,
+            git::git_apply_patch,
+            git::git_diff,
+            git::git_diff_file_refs,
+            git::git_discard_changes,
+            git::git_discard_all,
+            git::git_ignored_size,
+            git::git_checkout_file,
+            git::git_file_at_ref,
+            git::git_log,
+            git::git_log_graph,
+            git::git_head_commit,
+            git::git_resolve_ref,
+            git::git_object_type,
+            git::git_file_history,
+            git::git_show,
+            git::git_upstream_status,
+            git::git_merge_base,
+            git::git_compare_refs,
+            git::git_stash,
+            git::git_stash_list,
+            git::git_stash_pop,
+            git::git_fetch,
+            git::git_fetch_prune,
+            git::git_stale_branches,
+            git::git_branches_containing,
+            git::git_pull,
+            git::git_push,
+            git::git_clone,
+            git::git_remotes,
+            git::git_add_remote,
+            git::git_rename_remote,
+            git::git_remove_remote,
+            git::git_list_tags,
+            git::git_create_tag,
+            git::git_blame,
+            git::git_blame_range,
+            git::git_repo_root,
+            git::git_repo_summary,
+            git::git_init,
+            git::git_set_user,
+            git::git_get_user,
+            git::git_unset_user,
+            git::git_merge,
+            git::git_rebase,
+            git::git_cherry_pick,
+            git::git_revert,
+            git::git_conflicts,
+            git::git_operation_abort,
+            git::git_operation_continue,
+            git::git_reset,
+            git::git_worktree_list,
+            git::git_worktree_add,
+            git::git_submodule_status,
+            git::git_submodule_update,
+        ])
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                app.state::<watch::WatcherState>().clear();
+                app.state::<watch::TailState>().clear();
+                app.state::<terminal::TerminalManager>().clear();
+            }
+        });
 }
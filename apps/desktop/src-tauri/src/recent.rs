@@ -0,0 +1,207 @@
+use crate::files::write_atomic;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State};
+
+/// Most recent projects kept; opening another drops the oldest.
+const MAX_RECENT_PROJECTS: usize = 20;
+
+const RECENT_PROJECTS_FILE: &str = "recent_projects.json";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RecentProject {
+    pub path: String,
+    pub name: String,
+    /// Unix epoch millis.
+    pub last_opened: u64,
+    /// Whether `path` still exists. Only filled in when listing; a missing
+    /// project stays in the list so a remounted drive brings it back.
+    #[serde(default, skip_deserializing)]
+    pub exists: bool,
+}
+
+/// Held while the list is changed. Each change loads the file, edits it and
+/// saves it again, so two at once would otherwise lose one of the edits.
+#[derive(Default)]
+pub struct RecentProjects(Mutex<()>);
+
+fn recent_file(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join(RECENT_PROJECTS_FILE))
+        .map_err(|e| e.to_string())
+}
+
+/// The stored list, most recent first. A missing or unreadable file is an
+/// empty list rather than an error, so a corrupt file can't lock the user
+/// out of opening anything.
+fn load(file: &Path) -> Vec<RecentProject> {
+    fs::read(file)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+/// Writes with `write_atomic`, so a crash can't leave half a list behind.
+fn save(file: &Path, projects: &[RecentProject]) -> Result<(), String> {
+    let Some(dir) = file.parent() else {
+        return Err(format!("{} has no parent directory", file.display()));
+    };
+    fs::create_dir_all(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+    let json = serde_json::to_vec_pretty(projects).map_err(|e| e.to_string())?;
+    write_atomic(file, &json).map_err(|e| format!("{}: {e}", file.display()))
+}
+
+impl RecentProjects {
+    /// Moves `path` to the front of the list in `file`, adding it if needed.
+    fn add(&self, file: &Path, path: &str, now: u64) -> Result<(), String> {
+        let _changing = self.0.lock().unwrap();
+        let mut projects = load(file);
+        projects.retain(|p| p.path != path);
+        let name = Path::new(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string());
+        projects.insert(
+            0,
+            RecentProject {
+                path: path.to_string(),
+                name,
+                last_opened: now,
+                exists: true,
+            },
+        );
+        projects.truncate(MAX_RECENT_PROJECTS);
+        save(file, &projects)
+    }
+
+    fn remove(&self, file: &Path, path: &str) -> Result<(), String> {
+        let _changing = self.0.lock().unwrap();
+        let mut projects = load(file);
+        let before = projects.len();
+        projects.retain(|p| p.path != path);
+        if projects.len() == before {
+            return Ok(());
+        }
+        save(file, &projects)
+    }
+}
+
+fn list(file: &Path) -> Vec<RecentProject> {
+    let mut projects = load(file);
+    for project in &mut projects {
+        project.exists = Path::new(&project.path).is_dir();
+    }
+    projects
+}
+
+#[tauri::command]
+pub fn add_recent_project(
+    app: AppHandle,
+    recent: State<'_, RecentProjects>,
+    path: String,
+) -> Result<(), String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64);
+    recent.add(&recent_file(&app)?, &path, now)
+}
+
+/// Recently opened projects, most recent first.
+#[tauri::command]
+pub fn list_recent_projects(app: AppHandle) -> Result<Vec<RecentProject>, String> {
+    Ok(list(&recent_file(&app)?))
+}
+
+#[tauri::command]
+pub fn remove_recent_project(
+    app: AppHandle,
+    recent: State<'_, RecentProjects>,
+    path: String,
+) -> Result<(), String> {
+    recent.remove(&recent_file(&app)?, &path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn paths(file: &Path) -> Vec<String> {
+        list(file).into_iter().map(|p| p.path).collect()
+    }
+
+    #[test]
+    fn add_moves_a_project_to_the_front_without_duplicating_it() {
+        let dir = TempDir::new().unwrap();
+        let recent = RecentProjects::default();
+        let file = dir.path().join("config/recent_projects.json");
+        recent.add(&file, "/projects/one", 1).unwrap();
+        recent.add(&file, "/projects/two", 2).unwrap();
+        recent.add(&file, "/projects/one", 3).unwrap();
+
+        let projects = list(&file);
+        assert_eq!(paths(&file), ["/projects/one", "/projects/two"]);
+        assert_eq!(projects[0].name, "one");
+        assert_eq!(projects[0].last_opened, 3);
+        assert!(!projects[0].exists);
+
+        recent.remove(&file, "/projects/one").unwrap();
+        recent.remove(&file, "/projects/missing").unwrap();
+        assert_eq!(paths(&file), ["/projects/two"]);
+        // Only the list itself is left, no temp files
+        assert_eq!(fs::read_dir(file.parent().unwrap()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn add_keeps_only_the_most_recent_projects() {
+        let dir = TempDir::new().unwrap();
+        let recent = RecentProjects::default();
+        let file = dir.path().join("recent_projects.json");
+        for i in 0..MAX_RECENT_PROJECTS + 5 {
+            recent
+                .add(&file, &format!("/projects/{i}"), i as u64)
+                .unwrap();
+        }
+        let paths = paths(&file);
+        assert_eq!(paths.len(), MAX_RECENT_PROJECTS);
+        assert_eq!(paths[0], format!("/projects/{}", MAX_RECENT_PROJECTS + 4));
+        assert_eq!(paths.last().unwrap(), "/projects/5");
+    }
+
+    #[test]
+    fn list_marks_which_projects_still_exist() {
+        let dir = TempDir::new().unwrap();
+        let recent = RecentProjects::default();
+        let file = dir.path().join("recent_projects.json");
+        let project = dir.path().to_string_lossy().to_string();
+        recent.add(&file, &project, 1).unwrap();
+        recent.add(&file, "/projects/gone", 2).unwrap();
+
+        let exists: Vec<bool> = list(&file).iter().map(|p| p.exists).collect();
+        assert_eq!(exists, [false, true]);
+        fs::write(&file, "{ not json").unwrap();
+        assert!(list(&file).is_empty());
+    }
+
+    #[test]
+    fn concurrent_adds_keep_every_project() {
+        let dir = TempDir::new().unwrap();
+        let recent = RecentProjects::default();
+        let file = dir.path().join("recent_projects.json");
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let (recent, file) = (&recent, &file);
+                scope.spawn(move || recent.add(file, &format!("/projects/{i}"), i).unwrap());
+            }
+        });
+
+        let mut paths = paths(&file);
+        paths.sort();
+        let expected: Vec<String> = (0..8).map(|i| format!("/projects/{i}")).collect();
+        assert_eq!(paths, expected);
+    }
+}
@@ -0,0 +1,2244 @@
+use crate::files::{looks_binary, real_case};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::ipc::Channel;
+use tauri::State;
+
+#[derive(Clone, Serialize)]
+pub struct DirEntry {
+    pub name: String,
+    pub path: String,
+    pub is_dir: bool,
+    /// File size in bytes. For directories, 0 unless `compute_sizes` is set.
+    pub size: u64,
+    /// Last modification time in unix epoch millis, if the platform and
+    /// metadata (e.g. not a broken symlink) provide one.
+    pub modified: Option<u64>,
+    pub kind: FileKind,
+    /// The entry is a symbolic link; `is_dir` and `size` describe its target,
+    /// except for links to directories under `SymlinkPolicy::Skip`.
+    pub is_symlink: bool,
+    /// Where a symbolic link points, as stored in the link.
+    pub target: Option<String>,
+    /// Git would ignore this entry. Only computed when files are included
+    /// (ignored entries are left out entirely with `respect_gitignore`), and
+    /// always false outside a git repository.
+    pub ignored: bool,
+    pub children: Vec<DirEntry>,
+    /// How many entries were left out of `children` because the directory
+    /// had more than `max_entries_per_dir`. 0 when nothing was cut.
+    pub truncated_count: usize,
+    /// The directory had more than `skip_if_over` entries, so its
+    /// `children` weren't scanned; `list_children` can load them on demand.
+    pub not_expanded: bool,
+    /// Whether the file looks binary, with `detect_binary`. `None` for
+    /// directories, or when the file couldn't be read.
+    pub binary: Option<bool>,
+}
+
+/// What a file holds, for picking an icon and syntax highlighting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileKind {
+    Directory,
+    Rust,
+    JavaScript,
+    TypeScript,
+    Python,
+    Go,
+    Html,
+    Css,
+    Markdown,
+    Json,
+    Toml,
+    Yaml,
+    Shell,
+    Docker,
+    Makefile,
+    Text,
+    Image,
+    Other,
+}
+
+/// Files recognized by their whole name, checked before the extension.
+const NAME_KINDS: &[(&str, FileKind)] = &[
+    ("Dockerfile", FileKind::Docker),
+    ("Containerfile", FileKind::Docker),
+    ("Makefile", FileKind::Makefile),
+    ("makefile", FileKind::Makefile),
+    ("GNUmakefile", FileKind::Makefile),
+    ("LICENSE", FileKind::Text),
+];
+
+/// Lowercase extensions and their kinds.
+const EXTENSION_KINDS: &[(&str, FileKind)] = &[
+    ("rs", FileKind::Rust),
+    ("js", FileKind::JavaScript),
+    ("mjs", FileKind::JavaScript),
+    ("cjs", FileKind::JavaScript),
+    ("jsx", FileKind::JavaScript),
+    ("ts", FileKind::TypeScript),
+    ("mts", FileKind::TypeScript),
+    ("cts", FileKind::TypeScript),
+    ("tsx", FileKind::TypeScript),
+    ("py", FileKind::Python),
+    ("go", FileKind::Go),
+    ("html", FileKind::Html),
+    ("htm", FileKind::Html),
+    ("css", FileKind::Css),
+    ("scss", FileKind::Css),
+    ("md", FileKind::Markdown),
+    ("markdown", FileKind::Markdown),
+    ("json", FileKind::Json),
+    ("toml", FileKind::Toml),
+    ("yaml", FileKind::Yaml),
+    ("yml", FileKind::Yaml),
+    ("sh", FileKind::Shell),
+    ("bash", FileKind::Shell),
+    ("zsh", FileKind::Shell),
+    ("mk", FileKind::Makefile),
+    ("txt", FileKind::Text),
+    ("png", FileKind::Image),
+    ("jpg", FileKind::Image),
+    ("jpeg", FileKind::Image),
+    ("gif", FileKind::Image),
+    ("svg", FileKind::Image),
+    ("webp", FileKind::Image),
+    ("ico", FileKind::Image),
+];
+
+impl FileKind {
+    pub(crate) fn classify(name: &str, is_dir: bool) -> Self {
+        if is_dir {
+            return FileKind::Directory;
+        }
+        if let Some((_, kind)) = NAME_KINDS.iter().find(|(n, _)| *n == name) {
+            return *kind;
+        }
+        let Some((_, extension)) = name.rsplit_once('.') else {
+            return FileKind::Other;
+        };
+        let extension = extension.to_ascii_lowercase();
+        EXTENSION_KINDS
+            .iter()
+            .find(|(e, _)| *e == extension)
+            .map_or(FileKind::Other, |(_, kind)| *kind)
+    }
+}
+
+/// One row of `scan_directory_flat`.
+#[derive(Serialize)]
+pub struct FlatEntry {
+    pub name: String,
+    pub path: String,
+    /// 0 for the root, 1 for its children and so on.
+    pub depth: u32,
+    pub is_dir: bool,
+    /// Index of the parent row; `None` only for the root.
+    pub parent_index: Option<usize>,
+}
+
+/// An ecosystem recognized by `detect_project_type`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Ecosystem {
+    Rust,
+    Node,
+    Python,
+    Go,
+    Java,
+    Ruby,
+    Php,
+}
+
+/// Files whose presence marks a directory as a project of that ecosystem.
+const PROJECT_MARKERS: &[(&str, Ecosystem)] = &[
+    ("Cargo.toml", Ecosystem::Rust),
+    ("package.json", Ecosystem::Node),
+    ("pyproject.toml", Ecosystem::Python),
+    ("setup.py", Ecosystem::Python),
+    ("requirements.txt", Ecosystem::Python),
+    ("go.mod", Ecosystem::Go),
+    ("pom.xml", Ecosystem::Java),
+    ("build.gradle", Ecosystem::Java),
+    ("build.gradle.kts", Ecosystem::Java),
+    ("Gemfile", Ecosystem::Ruby),
+    ("composer.json", Ecosystem::Php),
+];
+
+#[derive(Serialize)]
+pub struct ProjectMarker {
+    pub ecosystem: Ecosystem,
+    /// The marker file that gave it away.
+    pub path: String,
+}
+
+/// A path the scan couldn't read, e.g. for lack of permission.
+#[derive(Clone, Serialize)]
+pub struct ScanError {
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ScanResult {
+    pub tree: DirEntry,
+    pub errors: Vec<ScanError>,
+    /// Set when `cancel_scan` stopped the scan; `tree` is then partial.
+    pub cancelled: bool,
+}
+
+/// Cancellation flags of the `scan_directory_cancellable` calls in flight,
+/// keyed by the frontend's scan id.
+#[derive(Default)]
+pub struct ActiveScans(Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+/// Recent `scan_directory` results, so asking for the same scan again
+/// shortly after (e.g. on window focus) doesn't walk the tree again.
+#[derive(Default)]
+pub struct ScanCache(Mutex<Vec<CachedScan>>);
+
+struct CachedScan {
+    root: String,
+    max_depth: u32,
+    options: ScanOptions,
+    scanned_at: Instant,
+    result: ScanResult,
+}
+
+/// How long a cached scan is served, 2s unless `TITAN_SCAN_CACHE_TTL_MS`
+/// is set at build time.
+fn scan_cache_ttl() -> Duration {
+    let millis = option_env!("TITAN_SCAN_CACHE_TTL_MS").and_then(|v| v.parse().ok());
+    Duration::from_millis(millis.unwrap_or(2000))
+}
+
+/// Most scans kept at once, 8 unless `TITAN_SCAN_CACHE_SIZE` is set at
+/// build time. The oldest is dropped first.
+fn scan_cache_size() -> usize {
+    option_env!("TITAN_SCAN_CACHE_SIZE")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(8)
+}
+
+impl CachedScan {
+    fn matches(&self, root: &str, max_depth: u32, options: &ScanOptions) -> bool {
+        self.root == root && self.max_depth == max_depth && self.options == *options
+    }
+}
+
+impl ScanCache {
+    fn get(&self, root: &str, max_depth: u32, options: &ScanOptions) -> Option<ScanResult> {
+        let ttl = scan_cache_ttl();
+        let mut scans = self.0.lock().unwrap();
+        scans.retain(|s| s.scanned_at.elapsed() < ttl);
+        scans
+            .iter()
+            .find(|s| s.matches(root, max_depth, options))
+            .map(|s| s.result.clone())
+    }
+
+    fn insert(&self, root: String, max_depth: u32, options: ScanOptions, result: ScanResult) {
+        let size = scan_cache_size();
+        if size == 0 {
+            return;
+        }
+        let mut scans = self.0.lock().unwrap();
+        scans.retain(|s| !s.matches(&root, max_depth, &options));
+        if scans.len() >= size {
+            scans.remove(0);
+        }
+        scans.push(CachedScan {
+            root,
+            max_depth,
+            options,
+            scanned_at: Instant::now(),
+            result,
+        });
+    }
+
+    /// Drops every cached scan whose tree overlaps `path`: scans of it, of a
+    /// directory inside it, or of one containing it.
+    pub fn invalidate(&self, path: &str) {
+        let path = Path::new(path);
+        self.0.lock().unwrap().retain(|s| {
+            let root = Path::new(&s.root);
+            !(root.starts_with(path) || path.starts_with(root))
+        });
+    }
+}
+
+/// Messages pushed to the frontend by `scan_directory_streamed`.
+#[derive(Serialize)]
+#[serde(tag = "event", content = "data", rename_all = "camelCase")]
+pub enum ScanEvent {
+    /// A fully scanned top-level child of the root. These arrive in
+    /// completion order, not sorted order.
+    Entry(DirEntry),
+    /// Sent once after every top-level child; `total` counts every entry
+    /// streamed, including nested ones, and `truncated_count` is the root's.
+    Done {
+        total: usize,
+        truncated_count: usize,
+        errors: Vec<ScanError>,
+    },
+}
+
+/// Optional scan behaviour. Every field has a default so the frontend only
+/// needs to send the options it cares about.
+#[derive(Clone, PartialEq, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ScanOptions {
+    /// Return files as leaf entries alongside directories.
+    pub include_files: bool,
+    /// Also skip anything git would ignore (`.gitignore` files at every
+    /// level, `.git/info/exclude` and the global excludes file). Has no
+    /// effect outside a git repository; `SKIP_DIRS` applies either way.
+    pub respect_gitignore: bool,
+    /// Directory names to skip on top of the base list.
+    pub extra_skip: Vec<String>,
+    /// Replaces `SKIP_DIRS` as the base list when set. `extra_skip` is still
+    /// added on top.
+    pub override_skip: Option<Vec<String>>,
+    /// Include dotfiles and dot-directories such as `.github`. `.git` is
+    /// left out regardless, and `SKIP_DIRS` still applies.
+    pub show_hidden: bool,
+    /// Order of entries within each directory.
+    pub sort: SortMode,
+    /// Put directories ahead of files, each group in `sort` order, rather
+    /// than mixing the two. Defaults to true.
+    pub group_dirs_first: Option<bool>,
+    /// Keep only the first this many entries of each directory, after
+    /// sorting. The parent's `truncated_count` says how many were dropped.
+    pub max_entries_per_dir: Option<usize>,
+    /// Give directories a `size`: the total of every regular file below
+    /// them on disk, whether or not it made it into the returned tree.
+    /// Symlinks inside aren't followed, and a followed link that loops back
+    /// to an ancestor is given 0.
+    pub compute_sizes: bool,
+    /// What to do with symbolic links to directories.
+    pub symlinks: SymlinkPolicy,
+    /// When non-empty, keep only files whose path relative to the root
+    /// matches one of these globs (e.g. `*.rs`, `src/**/*.toml`).
+    /// Directories are still descended into. Patterns that don't parse are
+    /// reported in `errors` and left out.
+    pub include_globs: Vec<String>,
+    /// Leave out directories that end up with no children. Needs
+    /// `include_files`; directories at `max_depth` are kept since their
+    /// contents weren't looked at.
+    pub prune_empty: bool,
+    /// More directories to skip, on top of the base list and `extra_skip`,
+    /// with control over case and where in the tree they apply.
+    pub skip_rules: Vec<SkipRule>,
+    /// Match the base skip list and `extra_skip` ignoring case, so
+    /// `Node_Modules` is skipped like `node_modules`. Rules in `skip_rules`
+    /// choose for themselves.
+    pub skip_case_insensitive: bool,
+    /// List directories with more than this many immediate entries (before
+    /// any filtering) without scanning inside them, flagged `not_expanded`.
+    pub skip_if_over: Option<usize>,
+    /// Fill in `binary` for files. Costs a read of each file's first few
+    /// KB, so it's off by default.
+    pub detect_binary: bool,
+    /// Respell `root` with its on-disk casing first, so paths in the tree
+    /// match what git reports on case-insensitive filesystems.
+    pub canonical_case: bool,
+}
+
+/// Skips directories called `name`, optionally only at one depth or below
+/// one path.
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkipRule {
+    pub name: String,
+    /// Match `name` ignoring case, e.g. so `Node_Modules` is caught too.
+    #[serde(default)]
+    pub case_insensitive: bool,
+    /// Only at this depth: 0 is the root's own children.
+    #[serde(default)]
+    pub depth: Option<u32>,
+    /// Only inside this directory, given relative to the root (`packages`
+    /// covers `packages/a/build` but not `build`).
+    #[serde(default)]
+    pub path_prefix: Option<String>,
+}
+
+impl SkipRule {
+    /// `relative` is the directory's path from the scan root, ending in its
+    /// own name.
+    fn matches(&self, name: &str, relative: &Path) -> bool {
+        let name_matches = if self.case_insensitive {
+            self.name.eq_ignore_ascii_case(name)
+        } else {
+            self.name == name
+        };
+        let depth = relative.components().count().saturating_sub(1);
+        let parent = relative.parent().unwrap_or(Path::new(""));
+        name_matches
+            && self.depth.is_none_or(|d| d as usize == depth)
+            && self
+                .path_prefix
+                .as_ref()
+                .is_none_or(|prefix| parent.starts_with(prefix))
+    }
+}
+
+/// Links to files are always listed (with `is_symlink` set); the policy
+/// decides what happens to links to directories.
+#[derive(Clone, Copy, PartialEq, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum SymlinkPolicy {
+    /// Don't treat them as directories: they're listed as leaves with
+    /// `is_dir` false, whether or not files are included, so the link is
+    /// visible but never opened.
+    #[default]
+    Skip,
+    /// Scan them like real directories. A link back to a directory above it
+    /// is listed without children, so cycles end there. Only such loops are
+    /// cut: two links to the same directory, or a link next to its target,
+    /// are each scanned in full. Cutting those too would make the tree
+    /// depend on which sibling the parallel walk reached first.
+    Follow,
+    /// List them with their `target` but don't scan inside.
+    ShowAsLink,
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum SortMode {
+    /// Case-insensitive by name.
+    #[default]
+    Alphabetical,
+    /// By name, comparing runs of digits as numbers so `file2` comes before
+    /// `file10`.
+    Natural,
+    /// Most recently modified first; entries without a time go last.
+    Modified,
+}
+
+impl SortMode {
+    fn compare(self, a: &Candidate, b: &Candidate) -> cmp::Ordering {
+        match self {
+            SortMode::Alphabetical => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            SortMode::Natural => natural_cmp(&a.name.to_lowercase(), &b.name.to_lowercase()),
+            SortMode::Modified => b
+                .modified()
+                .cmp(&a.modified())
+                .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase())),
+        }
+    }
+}
+
+/// Compares strings character by character, except that runs of ASCII
+/// digits compare by numeric value. Equal values with different leading
+/// zeros order the shorter run first.
+fn natural_cmp(a: &str, b: &str) -> cmp::Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (Some(x), Some(y)) = (a.chars().next(), b.chars().next()) else {
+            return a.len().cmp(&b.len());
+        };
+        if x.is_ascii_digit() && y.is_ascii_digit() {
+            let a_end = a.find(|c: char| !c.is_ascii_digit()).unwrap_or(a.len());
+            let b_end = b.find(|c: char| !c.is_ascii_digit()).unwrap_or(b.len());
+            let a_digits = a[..a_end].trim_start_matches('0');
+            let b_digits = b[..b_end].trim_start_matches('0');
+            let ordering = a_digits
+                .len()
+                .cmp(&b_digits.len())
+                .then_with(|| a_digits.cmp(b_digits))
+                .then_with(|| a_end.cmp(&b_end));
+            if ordering != cmp::Ordering::Equal {
+                return ordering;
+            }
+            a = &a[a_end..];
+            b = &b[b_end..];
+        } else {
+            if x != y {
+                return x.cmp(&y);
+            }
+            a = &a[x.len_utf8()..];
+            b = &b[y.len_utf8()..];
+        }
+    }
+}
+
+impl ScanOptions {
+    /// Directory-name match against the base skip list and `extra_skip`
+    /// (exact unless `skip_case_insensitive`), then `skip_rules`. `relative`
+    /// is the directory's path from the scan root.
+    fn skips_dir(&self, name: &str, relative: &Path) -> bool {
+        let is = |s: &str| {
+            if self.skip_case_insensitive {
+                s.eq_ignore_ascii_case(name)
+            } else {
+                s == name
+            }
+        };
+        let in_base = match &self.override_skip {
+            Some(list) => list.iter().any(|s| is(s)),
+            None => SKIP_DIRS.iter().any(|s| is(s)),
+        };
+        in_base
+            || self.extra_skip.iter().any(|s| is(s))
+            || self.skip_rules.iter().any(|r| r.matches(name, relative))
+    }
+
+    /// Whether the scan needs gitignore matchers, either to filter entries
+    /// or to flag them as `ignored`.
+    fn uses_gitignore(&self) -> bool {
+        self.respect_gitignore || self.include_files
+    }
+}
+
+pub(crate) const SKIP_DIRS: &[&str] = &[
+    "node_modules",
+    "target",
+    "dist",
+    ".git",
+    ".svn",
+    ".hg",
+    "__pycache__",
+    ".next",
+    ".nuxt",
+    "build",
+];
+
+/// Depth used when the frontend doesn't ask for a specific one.
+const DEFAULT_MAX_DEPTH: u32 = 4;
+
+fn modified_millis(metadata: &fs::Metadata) -> Option<u64> {
+    epoch_millis(metadata.modified().ok()?)
+}
+
+/// `time` in unix epoch millis; `None` for times before the epoch.
+pub(crate) fn epoch_millis(time: SystemTime) -> Option<u64> {
+    let since_epoch = time.duration_since(UNIX_EPOCH).ok()?;
+    Some(since_epoch.as_millis() as u64)
+}
+
+/// Walks up from `dir` looking for the enclosing repository's working tree.
+fn find_repo_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|d| d.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// Builds a matcher for the `.gitignore` in `dir`, or `None` if there's
+/// nothing to match against.
+fn dir_matcher(dir: &Path) -> Option<Gitignore> {
+    file_matcher(dir, &dir.join(".gitignore"))
+}
+
+/// Builds a matcher for the ignore file at `file`, with patterns relative
+/// to `dir`, or `None` if there's no such file.
+fn file_matcher(dir: &Path, file: &Path) -> Option<Gitignore> {
+    if !file.is_file() {
+        return None;
+    }
+    let mut builder = GitignoreBuilder::new(dir);
+    // A partially-invalid ignore file still contributes its valid globs
+    let _ = builder.add(file);
+    builder.build().ok()
+}
+
+/// Collects the matchers that apply to `root`, outermost first: the global
+/// excludes file, `.git/info/exclude`, the repo root's `.gitignore`, then
+/// every `.gitignore` between the repo root and `root`. Later ones win, so
+/// as in git any `.gitignore` overrides both excludes files.
+fn root_matchers(root: &Path) -> Vec<Gitignore> {
+    let Some(repo_root) = find_repo_root(root) else {
+        return Vec::new();
+    };
+
+    let mut matchers = Vec::new();
+    let (global, _) = Gitignore::global();
+    if !global.is_empty() {
+        matchers.push(global);
+    }
+
+    let exclude = repo_root.join(".git").join("info").join("exclude");
+    matchers.extend(file_matcher(&repo_root, &exclude));
+    matchers.extend(dir_matcher(&repo_root));
+
+    let Ok(relative) = root.strip_prefix(&repo_root) else {
+        return matchers;
+    };
+    let mut dir = repo_root.clone();
+    for component in relative.components() {
+        dir.push(component);
+        matchers.extend(dir_matcher(&dir));
+    }
+    matchers
+}
+
+/// The innermost matcher with an opinion wins, so a nested `!pattern` can
+/// re-include something an outer `.gitignore` excluded.
+fn is_ignored(matchers: &[Gitignore], path: &Path, is_dir: bool) -> bool {
+    for matcher in matchers.iter().rev() {
+        let m = matcher.matched(path, is_dir);
+        if m.is_ignore() {
+            return true;
+        }
+        if m.is_whitelist() {
+            return false;
+        }
+    }
+    false
+}
+
+/// An entry that survived filtering but whose subtree hasn't been scanned.
+struct Candidate {
+    name: String,
+    path: PathBuf,
+    is_dir: bool,
+    is_symlink: bool,
+    ignored: bool,
+    metadata: Option<fs::Metadata>,
+}
+
+impl Candidate {
+    fn modified(&self) -> Option<u64> {
+        self.metadata.as_ref().and_then(modified_millis)
+    }
+}
+
+/// State shared by every level of one scan. Subtrees run on the rayon pool,
+/// so anything they record goes behind a lock.
+/// An open directory, which holds a file descriptor until it's dropped.
+type Listing = Box<dyn Iterator<Item = std::io::Result<fs::DirEntry>> + Send>;
+
+struct Scanner<'a> {
+    options: &'a ScanOptions,
+    max_depth: u32,
+    /// Matchers that apply at the scan root; deeper levels extend these.
+    matchers: Vec<Gitignore>,
+    errors: Mutex<Vec<ScanError>>,
+    /// Checked before each directory is read; once set, the remaining
+    /// directories come back empty.
+    cancelled: Arc<AtomicBool>,
+    /// With `SymlinkPolicy::Follow`, the canonical root, which starts the
+    /// chain of real directories used to detect link cycles. Empty otherwise.
+    root_ancestors: Vec<PathBuf>,
+    /// Globs are matched against paths relative to this.
+    root: PathBuf,
+    /// Compiled `include_globs`, or `None` when every file is kept.
+    include: Option<GlobSet>,
+    /// Lists a directory; `fs::read_dir` except in tests that count reads
+    /// or open handles.
+    list_dir: fn(&Path) -> std::io::Result<Listing>,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(root: &Path, max_depth: u32, options: &'a ScanOptions) -> Self {
+        let matchers = if options.uses_gitignore() {
+            root_matchers(root)
+        } else {
+            Vec::new()
+        };
+        let mut errors = Vec::new();
+        let include = if options.include_globs.is_empty() {
+            None
+        } else {
+            let mut builder = GlobSetBuilder::new();
+            for pattern in &options.include_globs {
+                match Glob::new(pattern) {
+                    Ok(glob) => {
+                        builder.add(glob);
+                    }
+                    Err(e) => errors.push(ScanError {
+                        path: pattern.clone(),
+                        message: e.to_string(),
+                    }),
+                }
+            }
+            // An empty builder always succeeds and matches nothing
+            builder.build().ok()
+        };
+        Scanner {
+            options,
+            max_depth,
+            matchers,
+            errors: Mutex::new(errors),
+            cancelled: Arc::default(),
+            root_ancestors: if options.symlinks == SymlinkPolicy::Follow {
+                fs::canonicalize(root).ok().into_iter().collect()
+            } else {
+                Vec::new()
+            },
+            root: root.to_path_buf(),
+            include,
+            list_dir: |dir| Ok(Box::new(fs::read_dir(dir)?)),
+        }
+    }
+
+    fn record_error(&self, path: &Path, error: std::io::Error) {
+        self.errors.lock().unwrap().push(ScanError {
+            path: path.to_string_lossy().to_string(),
+            message: error.to_string(),
+        });
+    }
+
+    fn into_errors(self) -> Vec<ScanError> {
+        let mut errors = self.errors.into_inner().unwrap();
+        errors.sort_by(|a, b| a.path.cmp(&b.path));
+        errors
+    }
+
+    /// Reads `dir` and applies every filter that only needs the entry itself.
+    /// Also returns the bytes under the entries filtered out, which still
+    /// count towards `dir`'s size.
+    fn read_candidates(&self, dir: &Path, matchers: &[Gitignore]) -> (Vec<Candidate>, u64) {
+        let entries = match (self.list_dir)(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                self.record_error(dir, e);
+                return (Vec::new(), 0);
+            }
+        };
+
+        let options = self.options;
+        let mut candidates = Vec::new();
+        // Sized after the loop, once `dir` is closed again
+        let mut filtered = Vec::new();
+
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    self.record_error(dir, e);
+                    continue;
+                }
+            };
+            let path = entry.path();
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(e) => {
+                    self.record_error(&path, e);
+                    continue;
+                }
+            };
+
+            // `file_type` doesn't follow symlinks, so look at the target to
+            // tell a linked file from a linked directory. A broken link is a
+            // file without metadata.
+            let is_symlink = file_type.is_symlink();
+            let metadata = if is_symlink {
+                fs::metadata(&path).ok()
+            } else {
+                entry.metadata().ok()
+            };
+            let to_dir = if is_symlink {
+                metadata.as_ref().is_some_and(|m| m.is_dir())
+            } else {
+                file_type.is_dir()
+            };
+            let skipped_link = is_symlink && to_dir && options.symlinks == SymlinkPolicy::Skip;
+            let is_dir = to_dir && !skipped_link;
+            if !to_dir && (!options.include_files || !self.includes(&path)) {
+                filtered.push((path, is_symlink, metadata));
+                continue;
+            }
+
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            // Skip hidden entries and known non-project dirs
+            let hidden = name.starts_with('.') && !options.show_hidden;
+            let relative = path.strip_prefix(&self.root).unwrap_or(&path);
+            if hidden || name == ".git" || (to_dir && options.skips_dir(&name, relative)) {
+                filtered.push((path, is_symlink, metadata));
+                continue;
+            }
+
+            let ignored = !matchers.is_empty() && is_ignored(matchers, &path, is_dir);
+            if ignored && options.respect_gitignore {
+                filtered.push((path, is_symlink, metadata));
+                continue;
+            }
+
+            candidates.push(Candidate {
+                name,
+                path,
+                is_dir,
+                is_symlink,
+                ignored,
+                metadata,
+            });
+        }
+
+        let filtered_size = filtered
+            .iter()
+            .map(|(path, is_symlink, metadata)| {
+                self.unlisted_size(path, *is_symlink, metadata.as_ref())
+            })
+            .sum();
+        (candidates, filtered_size)
+    }
+
+    /// Whether `dir` has more entries than `skip_if_over` allows. Only
+    /// counts as far as the limit, so a huge directory stays cheap.
+    fn is_over_limit(&self, dir: &Path) -> bool {
+        let Some(limit) = self.options.skip_if_over else {
+            return false;
+        };
+        match (self.list_dir)(dir) {
+            Ok(entries) => entries.take(limit.saturating_add(1)).count() > limit,
+            // Left for the scan itself to report
+            Err(_) => false,
+        }
+    }
+
+    /// Bytes in the regular files under `dir`, for the parts of the tree
+    /// the scan doesn't descend into: past the depth limit, filtered out or
+    /// not expanded. `dir` itself may be a link, but links inside it aren't
+    /// followed. 0 unless `compute_sizes` is set or once the scan is
+    /// cancelled; anything that can't be read counts as empty.
+    fn unscanned_size(&self, dir: &Path) -> u64 {
+        if !self.options.compute_sizes || self.cancelled.load(Ordering::Relaxed) {
+            return 0;
+        }
+        let Ok(listing) = (self.list_dir)(dir) else {
+            return 0;
+        };
+        // Closed before going deeper, so only one directory is open at a time
+        let entries: Vec<_> = listing.flatten().collect();
+        entries
+            .into_iter()
+            .map(|entry| match entry.file_type() {
+                Ok(t) if t.is_dir() => self.unscanned_size(&entry.path()),
+                Ok(t) if t.is_file() => entry.metadata().map_or(0, |m| m.len()),
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// What an entry left out of the tree adds to its parent's size. Links
+    /// add nothing, as they aren't followed for sizes.
+    fn unlisted_size(&self, path: &Path, is_symlink: bool, metadata: Option<&fs::Metadata>) -> u64 {
+        match metadata {
+            _ if is_symlink => 0,
+            Some(m) if m.is_dir() => self.unscanned_size(path),
+            Some(m) if m.is_file() => m.len(),
+            _ => 0,
+        }
+    }
+
+    /// Whether a file passes `include_globs`.
+    fn includes(&self, path: &Path) -> bool {
+        match &self.include {
+            Some(globs) => globs.is_match(path.strip_prefix(&self.root).unwrap_or(path)),
+            None => true,
+        }
+    }
+
+    /// The chain of real directories to scan `candidate`'s subtree with, or
+    /// `None` if it isn't scanned: files, links shown as links, and followed
+    /// links that lead back to one of `ancestors`. The chain stays empty
+    /// unless links are followed.
+    fn descend_chain(&self, candidate: &Candidate, ancestors: &[PathBuf]) -> Option<Vec<PathBuf>> {
+        if !candidate.is_dir {
+            return None;
+        }
+        match self.options.symlinks {
+            SymlinkPolicy::ShowAsLink if candidate.is_symlink => None,
+            SymlinkPolicy::Follow => {
+                // Real directories extend the parent's canonical path, so
+                // only links need resolving
+                let real = match ancestors.last() {
+                    Some(parent) if !candidate.is_symlink => parent.join(&candidate.name),
+                    _ => fs::canonicalize(&candidate.path).ok()?,
+                };
+                if ancestors.contains(&real) {
+                    return None;
+                }
+                let mut chain = ancestors.to_vec();
+                chain.push(real);
+                Some(chain)
+            }
+            _ => Some(Vec::new()),
+        }
+    }
+
+    /// Turns a candidate into a `DirEntry`, scanning its subtree if it's a
+    /// directory. `None` when `prune_empty` drops it. Also returns what the
+    /// entry adds to its parent's size, which counts even when it's dropped.
+    fn scan_entry(
+        &self,
+        candidate: Candidate,
+        depth: u32,
+        matchers: &[Gitignore],
+        ancestors: &[PathBuf],
+    ) -> (Option<DirEntry>, u64) {
+        let mut chain = self.descend_chain(&candidate, ancestors);
+        // A followed directory without a chain leads back to an ancestor,
+        // which is already being sized
+        let loops =
+            candidate.is_dir && chain.is_none() && self.options.symlinks == SymlinkPolicy::Follow;
+        let not_expanded =
+            chain.is_some() && depth + 1 < self.max_depth && self.is_over_limit(&candidate.path);
+        if not_expanded {
+            chain = None;
+        }
+        let looked_inside = chain.is_some() && depth + 1 < self.max_depth;
+        let (mut children, truncated_count, subtree_size) = match chain {
+            Some(chain) => {
+                let nested = if self.options.uses_gitignore() {
+                    dir_matcher(&candidate.path)
+                } else {
+                    None
+                };
+                match nested {
+                    Some(nested) => {
+                        let mut nested_matchers = matchers.to_vec();
+                        nested_matchers.push(nested);
+                        self.scan_recursive(&candidate.path, depth + 1, &nested_matchers, &chain)
+                    }
+                    None => self.scan_recursive(&candidate.path, depth + 1, matchers, &chain),
+                }
+            }
+            None if candidate.is_dir && !loops => {
+                (Vec::new(), 0, self.unscanned_size(&candidate.path))
+            }
+            None => (Vec::new(), 0, 0),
+        };
+
+        let size = match &candidate.metadata {
+            _ if candidate.is_dir && self.options.compute_sizes => subtree_size,
+            Some(m) if m.is_file() => m.len(),
+            _ => 0,
+        };
+        // A linked file or directory is sized at its target, but sizes don't
+        // follow links so the parent doesn't count it
+        let parent_share = if candidate.is_symlink { 0 } else { size };
+
+        let empty = children.is_empty() && truncated_count == 0;
+        if self.options.prune_empty && self.options.include_files && looked_inside && empty {
+            return (None, parent_share);
+        }
+        if candidate.ignored {
+            // Everything inside an ignored directory is ignored too
+            mark_ignored(&mut children);
+        }
+        let kind = FileKind::classify(&candidate.name, candidate.is_dir);
+        let modified = candidate.modified();
+        let target = if candidate.is_symlink {
+            fs::read_link(&candidate.path)
+                .ok()
+                .map(|t| t.to_string_lossy().to_string())
+        } else {
+            None
+        };
+        let binary = if self.options.detect_binary && !candidate.is_dir {
+            looks_binary(&candidate.path).ok()
+        } else {
+            None
+        };
+
+        let entry = DirEntry {
+            name: candidate.name,
+            path: candidate.path.to_string_lossy().to_string(),
+            is_dir: candidate.is_dir,
+            size,
+            modified,
+            kind,
+            is_symlink: candidate.is_symlink,
+            target,
+            ignored: candidate.ignored,
+            children,
+            truncated_count,
+            not_expanded,
+            binary,
+        };
+        (Some(entry), parent_share)
+    }
+
+    /// The candidates in `dir` in display order, cut to
+    /// `max_entries_per_dir`, along with how many were cut and the bytes
+    /// under everything filtered or cut. Sorting and cutting happen before
+    /// any subtree is scanned so dropped entries cost nothing beyond their
+    /// size.
+    fn sorted_candidates(
+        &self,
+        dir: &Path,
+        matchers: &[Gitignore],
+    ) -> (Vec<Candidate>, usize, u64) {
+        let (mut candidates, mut dropped_size) = self.read_candidates(dir, matchers);
+
+        let sort = self.options.sort;
+        if self.options.group_dirs_first.unwrap_or(true) {
+            candidates.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| sort.compare(a, b)));
+        } else {
+            candidates.sort_by(|a, b| sort.compare(a, b));
+        }
+
+        let limit = self.options.max_entries_per_dir.unwrap_or(usize::MAX);
+        let truncated = candidates.len().saturating_sub(limit);
+        for dropped in candidates.drain(limit.min(candidates.len())..) {
+            dropped_size +=
+                self.unlisted_size(&dropped.path, dropped.is_symlink, dropped.metadata.as_ref());
+        }
+        (candidates, truncated, dropped_size)
+    }
+
+    /// Sibling subtrees are scanned in parallel on the rayon pool. The
+    /// collect keeps the sorted order, so the result is identical to a
+    /// serial walk. Returns the entries, how many were truncated and the
+    /// size of `dir`, summed from its children so nothing already scanned
+    /// is read twice.
+    fn scan_recursive(
+        &self,
+        dir: &Path,
+        depth: u32,
+        matchers: &[Gitignore],
+        ancestors: &[PathBuf],
+    ) -> (Vec<DirEntry>, usize, u64) {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return (Vec::new(), 0, 0);
+        }
+        if depth >= self.max_depth {
+            return (Vec::new(), 0, self.unscanned_size(dir));
+        }
+
+        let (candidates, truncated, mut size) = self.sorted_candidates(dir, matchers);
+        let scanned: Vec<_> = candidates
+            .into_par_iter()
+            .map(|candidate| self.scan_entry(candidate, depth, matchers, ancestors))
+            .collect();
+        let mut entries = Vec::with_capacity(scanned.len());
+        for (entry, share) in scanned {
+            size += share;
+            entries.extend(entry);
+        }
+        (entries, truncated, size)
+    }
+
+    /// Scans the children of the root the scanner was created for.
+    fn scan_root(&self, root: &Path) -> (Vec<DirEntry>, usize, u64) {
+        self.scan_recursive(root, 0, &self.matchers, &self.root_ancestors)
+    }
+}
+
+fn mark_ignored(entries: &mut [DirEntry]) {
+    for entry in entries {
+        entry.ignored = true;
+        mark_ignored(&mut entry.children);
+    }
+}
+
+/// Appends `entry` and its subtree to `out` in pre-order.
+fn flatten(entry: DirEntry, depth: u32, parent_index: Option<usize>, out: &mut Vec<FlatEntry>) {
+    let index = out.len();
+    out.push(FlatEntry {
+        name: entry.name,
+        path: entry.path,
+        depth,
+        is_dir: entry.is_dir,
+        parent_index,
+    });
+    for child in entry.children {
+        flatten(child, depth + 1, Some(index), out);
+    }
+}
+
+fn count_entries(entry: &DirEntry) -> usize {
+    1 + entry.children.iter().map(count_entries).sum::<usize>()
+}
+
+/// Scans `root` up to `max_depth` levels below it (default 4).
+///
+/// The root entry is always returned; a depth of 0 yields the root with no
+/// children rather than an empty tree. Directories that couldn't be read are
+/// left empty in the tree and listed in `errors`.
+///
+/// The same scan asked for again within a couple of seconds is answered
+/// from `ScanCache`, unless `invalidate_scan_cache` or a watcher event for
+/// the tree has cleared it in between.
+#[tauri::command]
+pub fn scan_directory(
+    cache: State<'_, ScanCache>,
+    root: String,
+    max_depth: Option<u32>,
+    options: Option<ScanOptions>,
+) -> ScanResult {
+    let options = options.unwrap_or_default();
+    let max_depth = max_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+    if let Some(result) = cache.get(&root, max_depth, &options) {
+        return result;
+    }
+    let result = scan_tree(root.clone(), max_depth, &options, Arc::default());
+    cache.insert(root, max_depth, options, result.clone());
+    result
+}
+
+/// `scan_directory` for several roots at once, e.g. the folders of a
+/// workspace, scanned in parallel and returned in the order given. A root
+/// that can't be read comes back with an empty tree and the failure in its
+/// own `errors`, without affecting the others.
+#[tauri::command]
+pub fn scan_directories(
+    cache: State<'_, ScanCache>,
+    roots: Vec<String>,
+    max_depth: Option<u32>,
+    options: Option<ScanOptions>,
+) -> Vec<ScanResult> {
+    scan_roots(&cache, roots, max_depth, options)
+}
+
+fn scan_roots(
+    cache: &ScanCache,
+    roots: Vec<String>,
+    max_depth: Option<u32>,
+    options: Option<ScanOptions>,
+) -> Vec<ScanResult> {
+    let options = options.unwrap_or_default();
+    let max_depth = max_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+    roots
+        .into_par_iter()
+        .map(|root| {
+            if let Some(result) = cache.get(&root, max_depth, &options) {
+                return result;
+            }
+            let result = scan_tree(root.clone(), max_depth, &options, Arc::default());
+            cache.insert(root, max_depth, options.clone(), result.clone());
+            result
+        })
+        .collect()
+}
+
+/// Forgets cached scans overlapping `root`, so the next `scan_directory`
+/// walks the disk again.
+#[tauri::command]
+pub fn invalidate_scan_cache(cache: State<'_, ScanCache>, root: String) {
+    cache.invalidate(&root);
+}
+
+fn scan_tree(
+    root: String,
+    max_depth: u32,
+    options: &ScanOptions,
+    cancelled: Arc<AtomicBool>,
+) -> ScanResult {
+    let root = if options.canonical_case {
+        // A missing root is left for the scan to report
+        match real_case(Path::new(&root)) {
+            Ok(real) => real.to_string_lossy().to_string(),
+            Err(_) => root,
+        }
+    } else {
+        root
+    };
+    let path = Path::new(&root);
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| root.clone());
+
+    let mut scanner = Scanner::new(path, max_depth, options);
+    scanner.cancelled = cancelled;
+    let (children, truncated_count, size) = scanner.scan_root(path);
+
+    let modified = fs::metadata(path).ok().as_ref().and_then(modified_millis);
+    let size = if options.compute_sizes { size } else { 0 };
+
+    ScanResult {
+        tree: DirEntry {
+            name,
+            path: root,
+            is_dir: true,
+            size,
+            modified,
+            kind: FileKind::Directory,
+            is_symlink: false,
+            target: None,
+            ignored: false,
+            children,
+            truncated_count,
+            not_expanded: false,
+            binary: None,
+        },
+        cancelled: scanner.cancelled.load(Ordering::Relaxed),
+        errors: scanner.into_errors(),
+    }
+}
+
+/// Like `scan_directory`, but runs off the main thread and stops early when
+/// `cancel_scan` is called with the same `scan_id`, returning whatever was
+/// scanned so far with `cancelled` set.
+#[tauri::command]
+pub async fn scan_directory_cancellable(
+    state: State<'_, ActiveScans>,
+    root: String,
+    scan_id: String,
+    max_depth: Option<u32>,
+    options: Option<ScanOptions>,
+) -> Result<ScanResult, String> {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    state
+        .0
+        .lock()
+        .unwrap()
+        .insert(scan_id.clone(), cancelled.clone());
+
+    let flag = cancelled.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let options = options.unwrap_or_default();
+        let max_depth = max_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+        scan_tree(root, max_depth, &options, flag)
+    })
+    .await
+    .map_err(|e| e.to_string());
+
+    // A newer scan may have reused the id; only forget our own flag
+    let mut scans = state.0.lock().unwrap();
+    if scans
+        .get(&scan_id)
+        .is_some_and(|f| Arc::ptr_eq(f, &cancelled))
+    {
+        scans.remove(&scan_id);
+    }
+    result
+}
+
+/// Like `scan_directory`, but off the main thread and holding at most
+/// `concurrency` directories open at a time, for huge trees where one open
+/// directory per pool thread could run into the file-descriptor limit. Each
+/// thread closes a directory before scanning below it. The
+/// scan gets a thread pool of that size to itself, so it neither waits on
+/// nor holds up the global pool that other scans and `project_stats` share.
+/// The result is the same as `scan_directory`'s; it just bypasses the cache.
+#[tauri::command]
+pub async fn scan_directory_async(
+    root: String,
+    max_depth: Option<u32>,
+    concurrency: usize,
+    options: Option<ScanOptions>,
+) -> Result<ScanResult, String> {
+    if concurrency == 0 {
+        return Err("concurrency must be at least 1".into());
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let options = options.unwrap_or_default();
+        let max_depth = max_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(concurrency)
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(pool.install(|| scan_tree(root, max_depth, &options, Arc::default())))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Asks the scan with `scan_id` to stop. Unknown or finished ids are
+/// ignored.
+#[tauri::command]
+pub fn cancel_scan(state: State<'_, ActiveScans>, scan_id: String) {
+    if let Some(flag) = state.0.lock().unwrap().get(&scan_id) {
+        flag.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Like `scan_directory`, but pushes each top-level subtree over `channel` as
+/// soon as it's scanned so the frontend can render progressively. A send
+/// failure for one subtree doesn't stop the others.
+#[tauri::command]
+pub async fn scan_directory_streamed(
+    root: String,
+    max_depth: Option<u32>,
+    options: Option<ScanOptions>,
+    channel: Channel<ScanEvent>,
+) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let options = options.unwrap_or_default();
+        let path = Path::new(&root);
+        let max_depth = max_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+        let scanner = Scanner::new(path, max_depth, &options);
+
+        let total = AtomicUsize::new(0);
+        let mut truncated_count = 0;
+        if max_depth > 0 {
+            let (candidates, truncated, _) = scanner.sorted_candidates(path, &scanner.matchers);
+            truncated_count = truncated;
+            candidates.into_par_iter().for_each(|candidate| {
+                let (entry, _) =
+                    scanner.scan_entry(candidate, 0, &scanner.matchers, &scanner.root_ancestors);
+                if let Some(entry) = entry {
+                    total.fetch_add(count_entries(&entry), Ordering::Relaxed);
+                    let _ = channel.send(ScanEvent::Entry(entry));
+                }
+            });
+        }
+
+        channel
+            .send(ScanEvent::Done {
+                total: total.into_inner(),
+                truncated_count,
+                errors: scanner.into_errors(),
+            })
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// The same scan as `scan_directory` as a pre-order list, root first, for
+/// virtualized rendering: rows come in display order and `parent_index`
+/// keeps the nesting. Fails if the root itself can't be read; other
+/// unreadable directories are just left without children.
+#[tauri::command]
+pub fn scan_directory_flat(
+    root: String,
+    max_depth: Option<u32>,
+    options: Option<ScanOptions>,
+) -> Result<Vec<FlatEntry>, String> {
+    let metadata = fs::metadata(&root).map_err(|e| format!("Cannot read {root}: {e}"))?;
+    if !metadata.is_dir() {
+        return Err(format!("{root} is not a directory"));
+    }
+
+    let options = options.unwrap_or_default();
+    let max_depth = max_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+    let result = scan_tree(root.clone(), max_depth, &options, Arc::default());
+    if let Some(error) = result.errors.iter().find(|e| e.path == root) {
+        return Err(format!("Cannot read {root}: {}", error.message));
+    }
+
+    let mut rows = Vec::with_capacity(count_entries(&result.tree));
+    flatten(result.tree, 0, None, &mut rows);
+    Ok(rows)
+}
+
+/// Markers directly inside `dir`, in `PROJECT_MARKERS` order.
+fn markers_in(dir: &Path, out: &mut Vec<ProjectMarker>) {
+    for (name, ecosystem) in PROJECT_MARKERS {
+        let path = dir.join(name);
+        if path.is_file() {
+            out.push(ProjectMarker {
+                ecosystem: *ecosystem,
+                path: path.to_string_lossy().to_string(),
+            });
+        }
+    }
+}
+
+/// The ecosystems `root` belongs to, judged by marker files at the root and
+/// in its immediate subdirectories, so a monorepo reports each of its parts.
+/// Root markers come first, then subdirectories by name. Hidden and
+/// `SKIP_DIRS` directories aren't looked in.
+#[tauri::command]
+pub fn detect_project_type(root: String) -> Result<Vec<ProjectMarker>, String> {
+    let dir = Path::new(&root);
+    let entries = fs::read_dir(dir).map_err(|e| format!("Cannot read {root}: {e}"))?;
+
+    let mut markers = Vec::new();
+    markers_in(dir, &mut markers);
+
+    let mut subdirs: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            !name.starts_with('.') && !SKIP_DIRS.contains(&name.as_str())
+        })
+        .map(|entry| entry.path())
+        .collect();
+    subdirs.sort();
+    for subdir in subdirs {
+        markers_in(&subdir, &mut markers);
+    }
+    Ok(markers)
+}
+
+/// Lists just the immediate children of `path`, each with empty `children`,
+/// so the tree can load lazily as folders are expanded. Unlike the full scan
+/// this fails loudly when `path` is missing, not a directory or unreadable,
+/// so the caller can tell that apart from an empty folder.
+#[tauri::command]
+pub fn list_children(path: String, options: Option<ScanOptions>) -> Result<Vec<DirEntry>, String> {
+    let options = options.unwrap_or_default();
+    let dir = Path::new(&path);
+    let metadata = fs::metadata(dir).map_err(|e| format!("Cannot read {path}: {e}"))?;
+    if !metadata.is_dir() {
+        return Err(format!("{path} is not a directory"));
+    }
+
+    let scanner = Scanner::new(dir, 1, &options);
+    let (children, _, _) = scanner.scan_root(dir);
+    match scanner.into_errors().into_iter().find(|e| e.path == path) {
+        Some(error) => Err(format!("Cannot read {path}: {}", error.message)),
+        None => Ok(children),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// A temp directory holding `paths`; those ending in `/` are created as
+    /// directories, the rest as files containing their own path.
+    fn fixture(paths: &[&str]) -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for path in paths {
+            let full = dir.path().join(path);
+            if path.ends_with('/') {
+                fs::create_dir_all(&full).unwrap();
+            } else {
+                fs::create_dir_all(full.parent().unwrap()).unwrap();
+                fs::write(&full, path).unwrap();
+            }
+        }
+        dir
+    }
+
+    fn scan(dir: &Path, max_depth: u32, options: &ScanOptions) -> ScanResult {
+        let root = dir.to_string_lossy().to_string();
+        scan_tree(root, max_depth, options, Arc::default())
+    }
+
+    fn names(entries: &[DirEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    /// Levels below `entry`, 0 when it has no children.
+    fn levels(entry: &DirEntry) -> u32 {
+        entry
+            .children
+            .iter()
+            .map(|c| levels(c) + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn depth_one_lists_only_the_root_children() {
+        let dir = fixture(&["a/b/c/", "d/"]);
+        let result = scan(dir.path(), 1, &ScanOptions::default());
+        assert_eq!(names(&result.tree.children), ["a", "d"]);
+        assert!(result.tree.children.iter().all(|c| c.children.is_empty()));
+        assert!(result.errors.is_empty());
+    }
+
+    #[test]
+    fn depth_zero_returns_the_root_alone() {
+        let dir = fixture(&["a/b/"]);
+        let result = scan(dir.path(), 0, &ScanOptions::default());
+        let root_name = dir.path().file_name().unwrap().to_string_lossy();
+        assert_eq!(result.tree.name, root_name);
+        assert_eq!(result.tree.path, dir.path().to_string_lossy());
+        assert!(result.tree.is_dir);
+        assert!(result.tree.children.is_empty());
+    }
+
+    #[test]
+    fn large_depth_reaches_the_bottom_of_the_tree() {
+        let dir = fixture(&["1/2/3/4/5/6/7/"]);
+        assert_eq!(
+            levels(&scan(dir.path(), 4, &ScanOptions::default()).tree),
+            4
+        );
+        assert_eq!(
+            levels(&scan(dir.path(), 100, &ScanOptions::default()).tree),
+            7
+        );
+    }
+
+    #[test]
+    fn include_files_lists_files_after_directories() {
+        let dir = fixture(&["b.txt", "A.md", "src/main.rs", "docs/", "Cargo.toml"]);
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(dir.path().join("b.txt"), dir.path().join("link.txt")).unwrap();
+
+        let without = scan(dir.path(), 4, &ScanOptions::default());
+        assert_eq!(names(&without.tree.children), ["docs", "src"]);
+
+        let options = ScanOptions {
+            include_files: true,
+            ..Default::default()
+        };
+        let tree = scan(dir.path(), 4, &options).tree;
+        let mut expected = vec!["docs", "src", "A.md", "b.txt", "Cargo.toml"];
+        if cfg!(unix) {
+            expected.push("link.txt");
+        }
+        assert_eq!(names(&tree.children), expected);
+        for entry in &tree.children[2..] {
+            assert!(!entry.is_dir, "{} is a file", entry.name);
+            assert!(entry.children.is_empty());
+        }
+        assert_eq!(names(&tree.children[1].children), ["main.rs"]);
+        if cfg!(unix) {
+            let link = tree.children.last().unwrap();
+            assert!(link.is_symlink && !link.is_dir);
+            assert_eq!(link.size, "b.txt".len() as u64);
+        }
+    }
+
+    #[test]
+    fn extra_skip_adds_to_the_base_list() {
+        let dir = fixture(&["build/", "target/", "vendor/", "src/"]);
+        let options = ScanOptions {
+            extra_skip: vec!["vendor".into()],
+            ..Default::default()
+        };
+        assert_eq!(names(&scan(dir.path(), 1, &options).tree.children), ["src"]);
+    }
+
+    #[test]
+    fn override_skip_replaces_the_base_list() {
+        let dir = fixture(&["build/", "target/", "vendor/", "src/"]);
+        let options = ScanOptions {
+            override_skip: Some(vec!["target".into()]),
+            ..Default::default()
+        };
+        let tree = scan(dir.path(), 1, &options).tree;
+        assert_eq!(names(&tree.children), ["build", "src", "vendor"]);
+
+        let options = ScanOptions {
+            override_skip: Some(vec!["target".into()]),
+            extra_skip: vec!["vendor".into()],
+            ..Default::default()
+        };
+        let tree = scan(dir.path(), 1, &options).tree;
+        assert_eq!(names(&tree.children), ["build", "src"]);
+    }
+
+    /// The default scan done one directory at a time, as it was before
+    /// sibling subtrees ran in parallel.
+    fn serial_scan(dir: &Path, depth: u32, max_depth: u32) -> Vec<(String, u32)> {
+        if depth >= max_depth {
+            return Vec::new();
+        }
+        let mut dirs: Vec<(String, PathBuf)> = fs::read_dir(dir)
+            .unwrap()
+            .map(Result::unwrap)
+            .filter(|e| e.file_type().unwrap().is_dir())
+            .map(|e| (e.file_name().to_string_lossy().to_string(), e.path()))
+            .filter(|(name, _)| !name.starts_with('.') && !SKIP_DIRS.contains(&name.as_str()))
+            .collect();
+        dirs.sort_by_key(|(name, _)| name.to_lowercase());
+        let mut out = Vec::new();
+        for (name, path) in dirs {
+            out.push((name, depth));
+            out.extend(serial_scan(&path, depth + 1, max_depth));
+        }
+        out
+    }
+
+    fn preorder(entries: &[DirEntry], depth: u32, out: &mut Vec<(String, u32)>) {
+        for entry in entries {
+            out.push((entry.name.clone(), depth));
+            preorder(&entry.children, depth + 1, out);
+        }
+    }
+
+    #[test]
+    fn parallel_scan_matches_a_serial_walk() {
+        let dir = tempfile::tempdir().unwrap();
+        // Four levels of eight directories, with mixed case and skipped names
+        let mut level = vec![dir.path().to_path_buf()];
+        for depth in 0..4 {
+            let mut next = Vec::new();
+            for parent in &level {
+                for i in 0..8 {
+                    let name = match (depth, i) {
+                        (1, 0) => "node_modules".to_string(),
+                        (_, 1) => format!("Dir{i}"),
+                        _ => format!("dir{}", 7 - i),
+                    };
+                    let path = parent.join(name);
+                    fs::create_dir(&path).unwrap();
+                    next.push(path);
+                }
+            }
+            level = next;
+        }
+
+        let expected = serial_scan(dir.path(), 0, 5);
+        let mut actual = Vec::new();
+        preorder(
+            &scan(dir.path(), 5, &ScanOptions::default()).tree.children,
+            0,
+            &mut actual,
+        );
+        assert!(expected.len() > 1000);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn kind_comes_from_the_extension_in_any_case() {
+        let cases = [
+            ("main.rs", FileKind::Rust),
+            ("App.TSX", FileKind::TypeScript),
+            ("index.test.js", FileKind::JavaScript),
+            ("config.yml", FileKind::Yaml),
+            ("logo.PNG", FileKind::Image),
+            ("archive.xyz", FileKind::Other),
+        ];
+        for (name, kind) in cases {
+            assert_eq!(FileKind::classify(name, false), kind, "{name}");
+        }
+        assert_eq!(FileKind::classify("src.rs", true), FileKind::Directory);
+    }
+
+    #[test]
+    fn kind_of_extensionless_files_comes_from_the_name() {
+        let cases = [
+            ("Dockerfile", FileKind::Docker),
+            ("Containerfile", FileKind::Docker),
+            ("Makefile", FileKind::Makefile),
+            ("GNUmakefile", FileKind::Makefile),
+            ("LICENSE", FileKind::Text),
+            ("README", FileKind::Other),
+            (".bashrc", FileKind::Other),
+        ];
+        for (name, kind) in cases {
+            assert_eq!(FileKind::classify(name, false), kind, "{name}");
+        }
+
+        let dir = fixture(&["Dockerfile", "Makefile"]);
+        let options = ScanOptions {
+            include_files: true,
+            ..Default::default()
+        };
+        let kinds: Vec<FileKind> = scan(dir.path(), 1, &options)
+            .tree
+            .children
+            .iter()
+            .map(|e| e.kind)
+            .collect();
+        assert_eq!(kinds, [FileKind::Docker, FileKind::Makefile]);
+    }
+
+    #[test]
+    fn natural_sort_compares_digit_runs_as_numbers() {
+        let dir = fixture(&["file10/", "file2/", "File1/", "file02/", "file/"]);
+        let sorted = |sort| {
+            let options = ScanOptions {
+                sort,
+                ..Default::default()
+            };
+            let tree = scan(dir.path(), 1, &options).tree;
+            names(&tree.children)
+                .into_iter()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            sorted(SortMode::Natural),
+            ["file", "File1", "file2", "file02", "file10"]
+        );
+        assert_eq!(
+            sorted(SortMode::Alphabetical),
+            ["file", "file02", "File1", "file10", "file2"]
+        );
+        assert_eq!(natural_cmp("file2", "file10"), cmp::Ordering::Less);
+    }
+
+    #[test]
+    fn show_hidden_lists_dot_directories_except_git() {
+        let dir = fixture(&[".github/workflows/", ".git/objects/", ".cache/", "src/"]);
+        let hidden = scan(dir.path(), 2, &ScanOptions::default()).tree;
+        assert_eq!(names(&hidden.children), ["src"]);
+
+        let options = ScanOptions {
+            show_hidden: true,
+            ..Default::default()
+        };
+        let shown = scan(dir.path(), 2, &options).tree;
+        assert_eq!(names(&shown.children), [".cache", ".github", "src"]);
+        assert_eq!(names(&shown.children[1].children), ["workflows"]);
+    }
+
+    #[test]
+    fn max_entries_per_dir_keeps_the_first_entries_and_counts_the_rest() {
+        let dir = fixture(&["e/", "d/", "c/x/", "c/y/", "c/z/", "b/", "a/", "f.txt"]);
+        let options = ScanOptions {
+            include_files: true,
+            max_entries_per_dir: Some(3),
+            ..Default::default()
+        };
+        let tree = scan(dir.path(), 2, &options).tree;
+        assert_eq!(names(&tree.children), ["a", "b", "c"]);
+        assert_eq!(tree.truncated_count, 3);
+        let c = &tree.children[2];
+        assert_eq!(names(&c.children), ["x", "y", "z"]);
+        assert_eq!(c.truncated_count, 0);
+
+        let tree = scan(dir.path(), 1, &ScanOptions::default()).tree;
+        assert_eq!(tree.children.len(), 5);
+        assert_eq!(tree.truncated_count, 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cancelling_mid_walk_leaves_the_rest_unscanned() {
+        use std::io::Write;
+        use std::process::Command;
+
+        // Sniffing the FIFO for `detect_binary` blocks the walk until a
+        // writer shows up, which gives the test a known point to cancel at
+        let dir = fixture(&["a/b/c/"]);
+        let fifo = dir.path().join("0.pipe");
+        assert!(Command::new("mkfifo")
+            .arg(&fifo)
+            .status()
+            .unwrap()
+            .success());
+        let options = ScanOptions {
+            include_files: true,
+            detect_binary: true,
+            group_dirs_first: Some(false),
+            ..Default::default()
+        };
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        // One thread, so entries are scanned one after another in order
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let flag = cancelled.clone();
+        let scan = std::thread::spawn(move || pool.install(|| scan_tree(root, 4, &options, flag)));
+        let mut writer = fs::OpenOptions::new().write(true).open(&fifo).unwrap();
+        cancelled.store(true, Ordering::Relaxed);
+        writer.write_all(b"text").unwrap();
+        drop(writer);
+
+        let result = scan.join().unwrap();
+        assert!(result.cancelled);
+        assert_eq!(names(&result.tree.children), ["0.pipe", "a"]);
+        assert!(result.tree.children[1].children.is_empty());
+    }
+
+    #[test]
+    fn compute_sizes_totals_the_whole_subtree_at_every_level() {
+        let dir = fixture(&[
+            "a/x.txt",
+            "a/b/y.txt",
+            "a/b/c/z.txt",
+            "a/b/.hidden",
+            "top.txt",
+        ]);
+        let bytes = |paths: &[&str]| paths.iter().map(|p| p.len() as u64).sum::<u64>();
+        let c = bytes(&["a/b/c/z.txt"]);
+        let b = c + bytes(&["a/b/y.txt", "a/b/.hidden"]);
+        let a = b + bytes(&["a/x.txt"]);
+        let root = a + bytes(&["top.txt"]);
+
+        let sizes = |max_depth, include_files| {
+            let options = ScanOptions {
+                include_files,
+                compute_sizes: true,
+                max_entries_per_dir: Some(1),
+                ..Default::default()
+            };
+            let tree = scan(dir.path(), max_depth, &options).tree;
+            let mut sizes = vec![tree.size];
+            let mut level = &tree.children;
+            while let Some(first) = level.first().filter(|e| e.is_dir) {
+                sizes.push(first.size);
+                level = &first.children;
+            }
+            sizes
+        };
+        // Neither files nor the depth limit nor truncation change the totals
+        assert_eq!(sizes(4, false), [root, a, b, c]);
+        assert_eq!(sizes(1, false), [root, a]);
+        assert_eq!(sizes(4, true), [root, a, b, c]);
+    }
+
+    /// A root holding `real/inner/`, `link` pointing at `real` and `self`
+    /// pointing back at the root itself.
+    #[cfg(unix)]
+    fn linked_fixture() -> TempDir {
+        let dir = fixture(&["real/inner/"]);
+        std::os::unix::fs::symlink("real", dir.path().join("link")).unwrap();
+        std::os::unix::fs::symlink(".", dir.path().join("self")).unwrap();
+        dir
+    }
+
+    #[cfg(unix)]
+    fn scan_links(dir: &TempDir, symlinks: SymlinkPolicy) -> Vec<DirEntry> {
+        let options = ScanOptions {
+            symlinks,
+            group_dirs_first: Some(false),
+            ..Default::default()
+        };
+        scan(dir.path(), 4, &options).tree.children
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skipped_links_are_listed_as_tagged_leaves() {
+        let dir = linked_fixture();
+        let entries = scan_links(&dir, SymlinkPolicy::Skip);
+        assert_eq!(names(&entries), ["link", "real", "self"]);
+        for (link, target) in [(&entries[0], "real"), (&entries[2], ".")] {
+            assert!(link.is_symlink && !link.is_dir, "{}", link.name);
+            assert_eq!(link.target.as_deref(), Some(target));
+            assert!(link.children.is_empty());
+            assert_eq!(link.size, 0);
+        }
+        assert_eq!(names(&entries[1].children), ["inner"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn links_shown_as_links_are_directories_without_children() {
+        let dir = linked_fixture();
+        let entries = scan_links(&dir, SymlinkPolicy::ShowAsLink);
+        assert_eq!(names(&entries), ["link", "real", "self"]);
+        for (link, target) in [(&entries[0], "real"), (&entries[2], ".")] {
+            assert!(link.is_symlink && link.is_dir, "{}", link.name);
+            assert_eq!(link.target.as_deref(), Some(target));
+            assert!(link.children.is_empty());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn followed_links_are_scanned_until_they_loop() {
+        let dir = linked_fixture();
+        let entries = scan_links(&dir, SymlinkPolicy::Follow);
+        assert_eq!(names(&entries), ["link", "real", "self"]);
+        assert!(entries.iter().all(|e| e.is_dir));
+        assert_eq!(names(&entries[0].children), ["inner"]);
+        assert_eq!(names(&entries[1].children), ["inner"]);
+        // `self` leads straight back to the root, so the cycle ends there
+        let looped = &entries[2];
+        assert!(looped.is_symlink && looped.children.is_empty());
+    }
+
+    fn preorder_ignored<'a>(entries: &'a [DirEntry], out: &mut Vec<(&'a str, bool)>) {
+        for entry in entries {
+            out.push((entry.name.as_str(), entry.ignored));
+            preorder_ignored(&entry.children, out);
+        }
+    }
+
+    #[test]
+    fn gitignored_entries_are_flagged_or_left_out() {
+        let dir = fixture(&[
+            ".git/",
+            "app.log",
+            "src/main.rs",
+            "src/debug.log",
+            "out/bin",
+        ]);
+        fs::write(dir.path().join(".gitignore"), "*.log\nout/\n").unwrap();
+        let mut options = ScanOptions {
+            include_files: true,
+            ..Default::default()
+        };
+
+        let tree = scan(dir.path(), 4, &options).tree;
+        let mut flagged = Vec::new();
+        preorder_ignored(&tree.children, &mut flagged);
+        assert_eq!(
+            flagged,
+            [
+                ("out", true),
+                ("bin", true),
+                ("src", false),
+                ("debug.log", true),
+                ("main.rs", false),
+                ("app.log", true),
+            ]
+        );
+
+        options.respect_gitignore = true;
+        let tree = scan(dir.path(), 4, &options).tree;
+        assert_eq!(names(&tree.children), ["src"]);
+        assert_eq!(names(&tree.children[0].children), ["main.rs"]);
+    }
+
+    #[test]
+    fn scan_cache_serves_repeats_until_invalidated() {
+        let dir = fixture(&["a/"]);
+        let root = dir.path().to_string_lossy().to_string();
+        let options = ScanOptions::default();
+        let cache = ScanCache::default();
+        assert!(cache.get(&root, 2, &options).is_none());
+        cache.insert(
+            root.clone(),
+            2,
+            options.clone(),
+            scan(dir.path(), 2, &options),
+        );
+
+        // A hit is the stored result, even though the disk has moved on
+        fs::create_dir(dir.path().join("b")).unwrap();
+        let hit = cache.get(&root, 2, &options).unwrap();
+        assert_eq!(names(&hit.tree.children), ["a"]);
+        assert!(cache.get(&root, 3, &options).is_none());
+        let hidden = ScanOptions {
+            show_hidden: true,
+            ..Default::default()
+        };
+        assert!(cache.get(&root, 2, &hidden).is_none());
+
+        // Only paths overlapping the scanned tree drop it
+        cache.invalidate(&format!("{root}-sibling"));
+        cache.invalidate("/elsewhere");
+        assert!(cache.get(&root, 2, &options).is_some());
+        cache.invalidate(&dir.path().join("a/deep").to_string_lossy());
+        assert!(cache.get(&root, 2, &options).is_none());
+    }
+
+    #[test]
+    fn include_globs_keep_matching_files_at_any_depth() {
+        let dir = fixture(&[
+            "build.rs",
+            "README.md",
+            "src/main.rs",
+            "src/notes.txt",
+            "src/deep/mod.rs",
+            "docs/guide.md",
+        ]);
+        let options = ScanOptions {
+            include_files: true,
+            include_globs: vec!["*.rs".into(), "[".into()],
+            ..Default::default()
+        };
+        let result = scan(dir.path(), 4, &options);
+        let tree = result.tree;
+        assert_eq!(names(&tree.children), ["docs", "src", "build.rs"]);
+        assert!(tree.children[0].children.is_empty());
+        let src = &tree.children[1];
+        assert_eq!(names(&src.children), ["deep", "main.rs"]);
+        assert_eq!(names(&src.children[0].children), ["mod.rs"]);
+        // The pattern that doesn't parse is reported and otherwise ignored
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].path, "[");
+    }
+
+    #[test]
+    fn flat_rows_come_in_pre_order_with_parent_links() {
+        let dir = fixture(&["b/y/", "b/x/deep/", "a/", "C/z/", "node_modules/m/"]);
+        let root = dir.path().to_string_lossy().to_string();
+        let rows = scan_directory_flat(root.clone(), Some(4), None).unwrap();
+
+        assert_eq!((rows[0].path.as_str(), rows[0].depth), (root.as_str(), 0));
+        assert_eq!(rows[0].parent_index, None);
+        let walked: Vec<(String, u32)> = rows[1..]
+            .iter()
+            .map(|r| (r.name.clone(), r.depth - 1))
+            .collect();
+        assert_eq!(walked, serial_scan(dir.path(), 0, 4));
+        for row in &rows[1..] {
+            let parent = &rows[row.parent_index.unwrap()];
+            assert_eq!(
+                Path::new(&row.path).parent().unwrap(),
+                Path::new(&parent.path)
+            );
+            assert_eq!(row.depth, parent.depth + 1);
+        }
+
+        let missing = dir.path().join("missing").to_string_lossy().to_string();
+        assert!(scan_directory_flat(missing, None, None).is_err());
+    }
+
+    #[test]
+    fn detect_project_type_reports_every_marker_found() {
+        let dir = fixture(&[
+            "package.json",
+            "Cargo.toml",
+            "backend/go.mod",
+            "node_modules/dep/package.json",
+            "a/b/setup.py",
+        ]);
+        let root = dir.path().to_string_lossy().to_string();
+        let markers: Vec<(Ecosystem, PathBuf)> = detect_project_type(root)
+            .unwrap()
+            .into_iter()
+            .map(|m| (m.ecosystem, PathBuf::from(m.path)))
+            .collect();
+        // The root's own markers first, then one level down; deeper or
+        // skipped directories don't count
+        assert_eq!(
+            markers,
+            [
+                (Ecosystem::Rust, dir.path().join("Cargo.toml")),
+                (Ecosystem::Node, dir.path().join("package.json")),
+                (Ecosystem::Go, dir.path().join("backend/go.mod")),
+            ]
+        );
+    }
+
+    static OPEN_READS: AtomicUsize = AtomicUsize::new(0);
+    static MAX_OPEN_READS: AtomicUsize = AtomicUsize::new(0);
+
+    /// Counted as open from the read until the listing is dropped.
+    struct CountedListing(fs::ReadDir);
+
+    impl Iterator for CountedListing {
+        type Item = std::io::Result<fs::DirEntry>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            std::thread::sleep(Duration::from_millis(1));
+            self.0.next()
+        }
+    }
+
+    impl Drop for CountedListing {
+        fn drop(&mut self) {
+            OPEN_READS.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    fn counting_read_dir(dir: &Path) -> std::io::Result<Listing> {
+        let entries = fs::read_dir(dir)?;
+        let now = OPEN_READS.fetch_add(1, Ordering::SeqCst) + 1;
+        MAX_OPEN_READS.fetch_max(now, Ordering::SeqCst);
+        Ok(Box::new(CountedListing(entries)))
+    }
+
+    #[test]
+    fn a_pool_of_two_holds_at_most_two_directories_open_at_once() {
+        let paths: Vec<String> = (0..12)
+            .flat_map(|i| {
+                [
+                    format!("d{i}/inner/deeper/x.txt"),
+                    format!("d{i}/.cache/nested/"),
+                ]
+            })
+            .collect();
+        let dir = fixture(&paths.iter().map(String::as_str).collect::<Vec<_>>());
+        // Sizes walk the hidden directories and the levels below the depth
+        // limit too
+        let options = ScanOptions {
+            compute_sizes: true,
+            ..Default::default()
+        };
+        let mut scanner = Scanner::new(dir.path(), 2, &options);
+        scanner.list_dir = counting_read_dir;
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        let (children, _, _) = pool.install(|| scanner.scan_root(dir.path()));
+
+        assert_eq!(children.len(), 12);
+        assert_eq!(children[0].size, "d0/inner/deeper/x.txt".len() as u64);
+        let max = MAX_OPEN_READS.load(Ordering::SeqCst);
+        assert!((1..=2).contains(&max), "{max} directories open at once");
+    }
+
+    #[test]
+    fn several_roots_come_back_in_order_with_a_missing_one_isolated() {
+        let first = fixture(&["a/", "a/one.txt"]);
+        let second = fixture(&["b/"]);
+        let missing = first.path().join("missing").to_string_lossy().to_string();
+        let roots = vec![
+            first.path().to_string_lossy().to_string(),
+            missing.clone(),
+            second.path().to_string_lossy().to_string(),
+        ];
+
+        let results = scan_roots(&ScanCache::default(), roots, None, None);
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(names(&results[0].tree.children), ["a"]);
+        assert!(results[0].errors.is_empty());
+        assert_eq!(results[1].tree.path, missing);
+        assert!(results[1].tree.children.is_empty());
+        assert_eq!(results[1].errors.len(), 1);
+        assert_eq!(results[1].errors[0].path, missing);
+        assert_eq!(names(&results[2].tree.children), ["b"]);
+        assert!(results[2].errors.is_empty());
+    }
+
+    #[test]
+    fn case_insensitive_skipping_covers_the_defaults_and_rules() {
+        let dir = fixture(&["Node_Modules/", "Vendor/", "src/", "src/TARGET/"]);
+
+        let tree = scan(dir.path(), 3, &ScanOptions::default()).tree;
+        assert_eq!(names(&tree.children), ["Node_Modules", "src", "Vendor"]);
+
+        let options = ScanOptions {
+            skip_case_insensitive: true,
+            skip_rules: vec![SkipRule {
+                name: "vendor".into(),
+                case_insensitive: true,
+                depth: None,
+                path_prefix: None,
+            }],
+            ..Default::default()
+        };
+        let tree = scan(dir.path(), 3, &options).tree;
+        assert_eq!(names(&tree.children), ["src"]);
+        assert!(tree.children[0].children.is_empty());
+    }
+
+    #[test]
+    fn a_root_only_rule_leaves_deeper_matches_alone() {
+        let dir = fixture(&["out/", "src/", "src/out/", "src/out/keep/"]);
+        let options = ScanOptions {
+            skip_rules: vec![SkipRule {
+                name: "out".into(),
+                case_insensitive: false,
+                depth: Some(0),
+                path_prefix: None,
+            }],
+            ..Default::default()
+        };
+
+        let tree = scan(dir.path(), 3, &options).tree;
+        assert_eq!(names(&tree.children), ["src"]);
+        assert_eq!(names(&tree.children[0].children), ["out"]);
+        assert_eq!(names(&tree.children[0].children[0].children), ["keep"]);
+    }
+
+    static LISTED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+    fn recording_read_dir(dir: &Path) -> std::io::Result<Listing> {
+        LISTED.lock().unwrap().push(dir.to_path_buf());
+        Ok(Box::new(fs::read_dir(dir)?))
+    }
+
+    #[test]
+    fn a_directory_over_skip_if_over_is_flagged_and_not_recursed() {
+        let dir = fixture(&[
+            "big/", "big/a/", "big/b/", "big/c/", "big/d/", "small/", "small/a/",
+        ]);
+        let options = ScanOptions {
+            skip_if_over: Some(3),
+            ..Default::default()
+        };
+        let mut scanner = Scanner::new(dir.path(), 4, &options);
+        scanner.list_dir = recording_read_dir;
+
+        let (children, _, _) = scanner.scan_root(dir.path());
+
+        assert_eq!(names(&children), ["big", "small"]);
+        assert!(children[0].not_expanded && children[0].children.is_empty());
+        assert!(!children[1].not_expanded);
+        assert_eq!(names(&children[1].children), ["a"]);
+        // Counting `big` went through the same reader, and nothing below it
+        // was opened
+        let big = dir.path().join("big");
+        let listed = LISTED.lock().unwrap();
+        assert_eq!(listed.iter().filter(|p| **p == big).count(), 1);
+        assert!(listed.iter().all(|p| *p == big || !p.starts_with(&big)));
+    }
+
+    #[test]
+    fn directories_come_first_unless_grouping_is_off() {
+        let dir = fixture(&["b.txt", "Beta/", "a.txt", "alpha/", "c/"]);
+        let options = ScanOptions {
+            include_files: true,
+            ..Default::default()
+        };
+        let tree = scan(dir.path(), 1, &options).tree;
+        assert_eq!(
+            names(&tree.children),
+            ["alpha", "Beta", "c", "a.txt", "b.txt"]
+        );
+
+        let interleaved = ScanOptions {
+            group_dirs_first: Some(false),
+            ..options
+        };
+        let tree = scan(dir.path(), 1, &interleaved).tree;
+        assert_eq!(
+            names(&tree.children),
+            ["a.txt", "alpha", "b.txt", "Beta", "c"]
+        );
+    }
+
+    #[test]
+    fn sizes_are_summed_without_reading_any_directory_twice() {
+        let dir = fixture(&["a/x.txt", "a/b/y.txt", "a/b/c/z.txt", "a/.cache/w.txt"]);
+        let options = ScanOptions {
+            compute_sizes: true,
+            ..Default::default()
+        };
+        let mut scanner = Scanner::new(dir.path(), 2, &options);
+        scanner.list_dir = recording_read_dir;
+
+        let (children, _, size) = scanner.scan_root(dir.path());
+
+        let bytes = ["a/x.txt", "a/b/y.txt", "a/b/c/z.txt", "a/.cache/w.txt"]
+            .iter()
+            .map(|p| p.len() as u64)
+            .sum::<u64>();
+        assert_eq!(size, bytes);
+        assert_eq!(children[0].size, bytes);
+        // Scanned, hidden and past-the-depth-limit directories alike are
+        // each listed exactly once
+        let listed = LISTED.lock().unwrap();
+        for relative in ["", "a", "a/b", "a/b/c", "a/.cache"] {
+            let path = dir.path().join(relative);
+            let count = listed.iter().filter(|p| p.as_path() == path).count();
+            assert_eq!(count, 1, "{relative}");
+        }
+    }
+
+    #[test]
+    fn detect_binary_marks_files_and_leaves_directories_alone() {
+        let dir = fixture(&["src/", "notes.txt"]);
+        fs::write(dir.path().join("blob.bin"), b"\0\x01\x02").unwrap();
+        let scan_with = |detect_binary| {
+            let options = ScanOptions {
+                include_files: true,
+                detect_binary,
+                ..Default::default()
+            };
+            let tree = scan(dir.path(), 1, &options).tree;
+            tree.children.iter().map(|e| e.binary).collect::<Vec<_>>()
+        };
+
+        // Directories first, then `blob.bin` and `notes.txt`
+        assert_eq!(scan_with(true), [None, Some(true), Some(false)]);
+        assert_eq!(scan_with(false), [None, None, None]);
+    }
+
+    #[test]
+    fn canonical_case_reports_the_root_as_spelled_on_disk() {
+        let dir = fixture(&["project/src/"]);
+        fs::write(dir.path().join("Probe"), "").unwrap();
+        if !dir.path().join("PROBE").exists() {
+            // A case-sensitive filesystem has no other spelling to fix
+            return;
+        }
+        let options = ScanOptions {
+            canonical_case: true,
+            ..Default::default()
+        };
+        let result = scan(&dir.path().join("PROJECT"), 2, &options);
+        let real = dir.path().join("project");
+        assert_eq!(result.tree.path, real.to_string_lossy());
+        assert_eq!(result.tree.name, "project");
+        assert_eq!(
+            result.tree.children[0].path,
+            real.join("src").to_string_lossy()
+        );
+    }
+
+    #[test]
+    fn gitignore_overrides_info_exclude() {
+        let dir = fixture(&[".git/info/", "keep.log", "drop.log", "notes.tmp"]);
+        fs::write(dir.path().join(".git/info/exclude"), "*.log\n*.tmp\n").unwrap();
+        fs::write(dir.path().join(".gitignore"), "!keep.log\n").unwrap();
+        let options = ScanOptions {
+            include_files: true,
+            respect_gitignore: true,
+            ..Default::default()
+        };
+
+        let tree = scan(dir.path(), 1, &options).tree;
+        assert_eq!(names(&tree.children), ["keep.log"]);
+    }
+}
@@ -0,0 +1,281 @@
+use crate::scan::SKIP_DIRS;
+use grep_matcher::Matcher;
+use grep_regex::RegexMatcherBuilder;
+use grep_searcher::sinks::Lossy;
+use grep_searcher::{BinaryDetection, SearcherBuilder};
+use ignore::{Walk, WalkBuilder};
+use serde::Serialize;
+use std::path::Path;
+
+/// A line matching a `search_content` pattern.
+#[derive(Serialize)]
+pub struct Match {
+    pub path: String,
+    /// 1-based.
+    pub line_number: u64,
+    /// The matching line, cut to `MAX_LINE_CHARS`.
+    pub line_text: String,
+    /// 1-based character column of the first match on the line.
+    pub column: usize,
+}
+
+/// Longest `line_text` returned, so a match in minified code doesn't ship
+/// the whole file to the frontend.
+const MAX_LINE_CHARS: usize = 500;
+
+/// Score for each matched query character.
+const MATCH_SCORE: i32 = 16;
+/// Bonus when a match starts a word (`main_view`, `MainView`, `main.rs`).
+const BOUNDARY_BONUS: i32 = 8;
+/// Bonus when a match directly follows the previous one.
+const CONSECUTIVE_BONUS: i32 = 8;
+/// Largest penalty a single gap between matches can cost, so one long gap
+/// doesn't outweigh everything else.
+const MAX_GAP_PENALTY: i32 = 3;
+/// Per-character score at or above which a match counts as strong: nearly
+/// every character lands on a word start or continues the previous match.
+const STRONG_SCORE: i32 = 20;
+
+fn is_boundary(chars: &[char], index: usize) -> bool {
+    let Some(prev) = index.checked_sub(1).map(|i| chars[i]) else {
+        return true;
+    };
+    let current = chars[index];
+    !prev.is_alphanumeric() || (prev.is_lowercase() && current.is_uppercase())
+}
+
+/// Case-insensitive subsequence match of `query` against `name`, scored so
+/// that matches on word starts and runs of adjacent characters win, e.g.
+/// `mvc` scores higher on `MainViewController.rs` than on `movie_clip.rs`.
+/// Returns `None` if `query` isn't a subsequence of `name`.
+fn fuzzy_score(query: &[char], name: &str) -> Option<i32> {
+    let chars: Vec<char> = name.chars().collect();
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+
+    // best[j]: best score for the query so far with its last character
+    // matched at position j
+    let mut best: Vec<Option<i32>> = vec![None; chars.len()];
+    for (i, &q) in query.iter().enumerate() {
+        let mut next = vec![None; chars.len()];
+        for j in (0..chars.len()).filter(|&j| lower[j] == q) {
+            let own = MATCH_SCORE
+                + if is_boundary(&chars, j) {
+                    BOUNDARY_BONUS
+                } else {
+                    0
+                };
+            let prior = if i == 0 {
+                Some(-(j as i32).min(MAX_GAP_PENALTY))
+            } else {
+                (0..j)
+                    .filter_map(|k| {
+                        best[k].map(|score| {
+                            let gap = (j - k - 1) as i32;
+                            if gap == 0 {
+                                score + CONSECUTIVE_BONUS
+                            } else {
+                                score - gap.min(MAX_GAP_PENALTY)
+                            }
+                        })
+                    })
+                    .max()
+            };
+            next[j] = prior.map(|score| score + own);
+        }
+        best = next;
+    }
+    best.into_iter().flatten().max()
+}
+
+/// Walks `root` the way the searches see it: hidden, gitignored and
+/// `SKIP_DIRS` entries are left out.
+pub(crate) fn walk(root: &Path) -> Walk {
+    WalkBuilder::new(root)
+        .filter_entry(|entry| {
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            !(is_dir && SKIP_DIRS.contains(&entry.file_name().to_string_lossy().as_ref()))
+        })
+        .build()
+}
+
+/// Files under `root` whose name fuzzy-matches `query`, best first, at most
+/// `limit` of them. Hidden, gitignored and `SKIP_DIRS` entries are left out.
+/// The walk stops early once `limit` strong matches have been found, so the
+/// results on a huge tree are good rather than exhaustive.
+#[tauri::command]
+pub fn search_files(root: String, query: String, limit: usize) -> Result<Vec<String>, String> {
+    let root = Path::new(&root);
+    if !root.is_dir() {
+        return Err(format!("{} is not a directory", root.display()));
+    }
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if query.is_empty() || limit == 0 {
+        return Ok(Vec::new());
+    }
+
+    let strong = STRONG_SCORE * query.len() as i32;
+    let mut matches: Vec<(i32, String)> = Vec::new();
+    let mut strong_matches = 0;
+    // Unreadable entries are skipped; a partial answer beats none here
+    for entry in walk(root).flatten() {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy();
+        let Some(score) = fuzzy_score(&query, &name) else {
+            continue;
+        };
+        matches.push((score, entry.path().to_string_lossy().to_string()));
+        if score >= strong {
+            strong_matches += 1;
+            if strong_matches >= limit {
+                break;
+            }
+        }
+    }
+
+    // Ties go to the shorter path, which is usually the less nested file
+    matches.sort_by(|(a, a_path), (b, b_path)| {
+        b.cmp(a)
+            .then(a_path.len().cmp(&b_path.len()))
+            .then(a_path.cmp(b_path))
+    });
+    matches.truncate(limit);
+    Ok(matches.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Lines under `root` matching the regex `pattern`, or the plain text
+/// `pattern` when `literal` is set. Skips the same entries as `search_files`
+/// as well as binary files, and stops after `max_results` matches.
+#[tauri::command]
+pub fn search_content(
+    root: String,
+    pattern: String,
+    case_sensitive: bool,
+    max_results: usize,
+    literal: Option<bool>,
+) -> Result<Vec<Match>, String> {
+    let root = Path::new(&root);
+    if !root.is_dir() {
+        return Err(format!("{} is not a directory", root.display()));
+    }
+    if pattern.is_empty() || max_results == 0 {
+        return Ok(Vec::new());
+    }
+    let matcher = RegexMatcherBuilder::new()
+        .case_insensitive(!case_sensitive)
+        .fixed_strings(literal.unwrap_or(false))
+        .line_terminator(Some(b'\n'))
+        .build(&pattern)
+        .map_err(|e| e.to_string())?;
+    let mut searcher = SearcherBuilder::new()
+        .binary_detection(BinaryDetection::quit(b'\0'))
+        .line_number(true)
+        .build();
+
+    let mut matches = Vec::new();
+    for entry in walk(root).flatten() {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let path = entry.path().to_string_lossy().to_string();
+        let sink = Lossy(|line_number, line: &str| {
+            let line = line.trim_end_matches(['\r', '\n']);
+            let column = match matcher.find(line.as_bytes()) {
+                Ok(Some(found)) => line[..found.start()].chars().count() + 1,
+                _ => 1,
+            };
+            matches.push(Match {
+                path: path.clone(),
+                line_number,
+                line_text: line.chars().take(MAX_LINE_CHARS).collect(),
+                column,
+            });
+            Ok(matches.len() < max_results)
+        });
+        // A file that can't be read is skipped like an unreadable entry
+        let _ = searcher.search_path(&matcher, entry.path(), sink);
+        if matches.len() >= max_results {
+            break;
+        }
+    }
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// A temp dir holding `files`, each `(path, contents)`.
+    fn fixture(files: &[(&str, &str)]) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for (path, contents) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    fn root(dir: &TempDir) -> String {
+        dir.path().to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn search_files_ranks_word_start_matches_first() {
+        let dir = fixture(&[
+            ("src/movie_clip.rs", ""),
+            ("src/views/MainViewController.rs", ""),
+            ("src/lib.rs", ""),
+        ]);
+        let found = search_files(root(&dir), "mvc".into(), 10).unwrap();
+        let names: Vec<&str> = found
+            .iter()
+            .map(|p| Path::new(p).file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, ["MainViewController.rs", "movie_clip.rs"]);
+    }
+
+    #[test]
+    fn search_content_reports_each_matching_line() {
+        let dir = fixture(&[
+            ("a.txt", "one\nthe needle\n"),
+            ("sub/b.rs", "  Needle();\nneedle\n"),
+            ("binary.dat", "needle\0"),
+            ("node_modules/dep.js", "needle"),
+        ]);
+        let search = |case_sensitive| {
+            let mut found: Vec<(String, u64, usize, String)> =
+                search_content(root(&dir), "needle".into(), case_sensitive, 100, None)
+                    .unwrap()
+                    .into_iter()
+                    .map(|m| {
+                        let path = Path::new(&m.path).strip_prefix(dir.path()).unwrap();
+                        let path = path.to_string_lossy().to_string();
+                        (path, m.line_number, m.column, m.line_text)
+                    })
+                    .collect();
+            found.sort();
+            found
+        };
+
+        assert_eq!(
+            search(false),
+            [
+                ("a.txt".to_string(), 2, 5, "the needle".to_string()),
+                ("sub/b.rs".to_string(), 1, 3, "  Needle();".to_string()),
+                ("sub/b.rs".to_string(), 2, 1, "needle".to_string()),
+            ]
+        );
+        assert_eq!(search(true).len(), 2);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::files::looks_binary;
+use crate::scan::FileKind;
+use crate::search::walk;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize)]
+pub struct LanguageStats {
+    pub language: FileKind,
+    pub files: usize,
+    /// Lines across the language's text files; binary files add none.
+    pub lines: u64,
+}
+
+#[derive(Serialize)]
+pub struct ProjectStats {
+    /// Most lines first, then most files, then by language name.
+    pub languages: Vec<LanguageStats>,
+    pub total_files: usize,
+    pub total_lines: u64,
+}
+
+/// Counts the lines of `path`, or `None` if `looks_binary` says it's
+/// binary, so stats agree with `DirEntry::binary`. A last line without a
+/// trailing newline still counts.
+fn count_lines(path: &Path) -> io::Result<Option<u64>> {
+    if looks_binary(path)? {
+        return Ok(None);
+    }
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0; 64 * 1024];
+    let mut lines = 0;
+    let mut last = b'\n';
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        let chunk = &buffer[..n];
+        lines += chunk.iter().filter(|&&b| b == b'\n').count() as u64;
+        last = chunk[n - 1];
+    }
+    if last != b'\n' {
+        lines += 1;
+    }
+    Ok(Some(lines))
+}
+
+/// File and line counts per language for the files under `root`, grouped by
+/// `FileKind`. Walks like `search_files`: hidden, gitignored and `SKIP_DIRS`
+/// entries are left out. Files that can't be read are skipped.
+#[tauri::command]
+pub fn project_stats(root: String) -> Result<ProjectStats, String> {
+    let root = Path::new(&root);
+    if !root.is_dir() {
+        return Err(format!("{} is not a directory", root.display()));
+    }
+
+    let files: Vec<PathBuf> = walk(root)
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .map(|entry| entry.into_path())
+        .collect();
+
+    let counted: Vec<(FileKind, u64)> = files
+        .par_iter()
+        .filter_map(|path| {
+            let name = path.file_name()?.to_string_lossy();
+            let lines = count_lines(path).ok()?;
+            Some((FileKind::classify(&name, false), lines.unwrap_or(0)))
+        })
+        .collect();
+
+    let mut by_kind: HashMap<FileKind, LanguageStats> = HashMap::new();
+    for (kind, lines) in counted {
+        let stats = by_kind.entry(kind).or_insert(LanguageStats {
+            language: kind,
+            files: 0,
+            lines: 0,
+        });
+        stats.files += 1;
+        stats.lines += lines;
+    }
+
+    let mut languages: Vec<LanguageStats> = by_kind.into_values().collect();
+    // The name is what `FileKind` serializes to, so ties read alphabetically
+    languages.sort_by_cached_key(|l| {
+        let name = format!("{:?}", l.language).to_lowercase();
+        (Reverse(l.lines), Reverse(l.files), name)
+    });
+    Ok(ProjectStats {
+        total_files: languages.iter().map(|l| l.files).sum(),
+        total_lines: languages.iter().map(|l| l.lines).sum(),
+        languages,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn fixture(files: &[(&str, &str)]) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for (path, contents) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    fn stats(dir: &TempDir) -> ProjectStats {
+        project_stats(dir.path().to_string_lossy().to_string()).unwrap()
+    }
+
+    fn rows(stats: &ProjectStats) -> Vec<(FileKind, usize, u64)> {
+        stats
+            .languages
+            .iter()
+            .map(|l| (l.language, l.files, l.lines))
+            .collect()
+    }
+
+    #[test]
+    fn languages_are_ordered_by_lines_then_files_then_name() {
+        let dir = fixture(&[
+            ("src/main.rs", "fn main() {}\n"),
+            ("src/lib.rs", "pub mod a;\npub mod b;"),
+            ("tool.py", "print(1)\nprint(2)\nprint(3)\n"),
+            ("run.sh", "a\nb\nc\n"),
+            ("README.md", "# Title\n"),
+            ("node_modules/dep/index.js", "ignored\n"),
+        ]);
+        let stats = stats(&dir);
+        assert_eq!(
+            rows(&stats),
+            [
+                (FileKind::Rust, 2, 3),
+                (FileKind::Python, 1, 3),
+                (FileKind::Shell, 1, 3),
+                (FileKind::Markdown, 1, 1),
+            ]
+        );
+        assert_eq!((stats.total_files, stats.total_lines), (5, 10));
+    }
+
+    #[test]
+    fn text_files_count_their_lines() {
+        let dir = fixture(&[("notes.txt", "one\ntwo\nthree"), ("empty.txt", "")]);
+        assert_eq!(count_lines(&dir.path().join("notes.txt")).unwrap(), Some(3));
+        assert_eq!(count_lines(&dir.path().join("empty.txt")).unwrap(), Some(0));
+    }
+
+    #[test]
+    fn binary_files_add_no_lines_but_still_count() {
+        let dir = fixture(&[("src/main.rs", "fn main() {}\n")]);
+        fs::write(dir.path().join("src/data.rs"), b"a\nb\n\0c\n").unwrap();
+        let latin1: Vec<u8> = (0..100)
+            .map(|i| if i % 4 == 0 { 0xe9 } else { b'a' })
+            .collect();
+        fs::write(dir.path().join("src/latin1.rs"), latin1).unwrap();
+
+        assert_eq!(count_lines(&dir.path().join("src/data.rs")).unwrap(), None);
+        // Mostly invalid UTF-8 is binary here just as it is in a scan
+        assert_eq!(
+            count_lines(&dir.path().join("src/latin1.rs")).unwrap(),
+            None
+        );
+        assert_eq!(rows(&stats(&dir)), [(FileKind::Rust, 3, 1)]);
+    }
+}
@@ -0,0 +1,191 @@
+use rayon::prelude::*;
+use serde::Serialize;
+use std::env;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a tool gets to answer `--version` before it's taken to have
+/// hung, e.g. waiting on a prompt.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Shows `path` in the platform's file manager, selected where the platform
+/// supports that. On Linux there's no common way to select a file, so the
+/// containing directory is opened instead.
+#[tauri::command]
+pub fn reveal_in_file_manager(path: String) -> Result<(), String> {
+    let target = Path::new(&path);
+    if target.symlink_metadata().is_err() {
+        return Err(format!("{path} does not exist"));
+    }
+    let mut child = reveal_command(target)
+        .spawn()
+        .map_err(|e| format!("Cannot open the file manager: {e}"))?;
+    // Reap it once it exits so it doesn't linger as a zombie
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn reveal_command(target: &Path) -> Command {
+    use std::os::windows::process::CommandExt;
+
+    // explorer only understands the quotes around the path, not around the
+    // whole argument as std would add them
+    let mut command = Command::new("explorer");
+    command.raw_arg(format!("/select,\"{}\"", target.display()));
+    command
+}
+
+#[cfg(target_os = "macos")]
+fn reveal_command(target: &Path) -> Command {
+    let mut command = Command::new("open");
+    command.arg("-R").arg(target);
+    command
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn reveal_command(target: &Path) -> Command {
+    let mut command = Command::new("xdg-open");
+    command.arg(target.parent().unwrap_or(target));
+    command
+}
+
+#[derive(Serialize)]
+pub struct ToolStatus {
+    pub name: String,
+    pub found: bool,
+    /// The first line the tool printed for `--version`, if it ran.
+    pub version: Option<String>,
+    /// Where on `PATH` it was found.
+    pub path: Option<String>,
+}
+
+/// Looks up each of `tools` (e.g. `git`, `rg`, `node`) on `PATH` and asks it
+/// for its version, so onboarding can check the environment in one call.
+/// Nothing is cached, since tools can be installed while the app runs.
+#[tauri::command]
+pub fn check_tools(tools: Vec<String>) -> Vec<ToolStatus> {
+    tools
+        .into_par_iter()
+        .map(|name| {
+            let found = find_on_path(&name);
+            ToolStatus {
+                found: found.is_some(),
+                version: found.as_deref().and_then(tool_version),
+                path: found.map(|p| p.to_string_lossy().to_string()),
+                name,
+            }
+        })
+        .collect()
+}
+
+/// The executable `name` resolves to, as the shell would find it.
+fn find_on_path(name: &str) -> Option<PathBuf> {
+    if name.is_empty() {
+        return None;
+    }
+    // A path rather than a bare name isn't looked up
+    if Path::new(name).components().count() > 1 {
+        return Some(PathBuf::from(name)).filter(|p| is_executable(p));
+    }
+    env::split_paths(&env::var_os("PATH")?).find_map(|dir| {
+        executable_names(name)
+            .into_iter()
+            .map(|n| dir.join(n))
+            .find(|p| is_executable(p))
+    })
+}
+
+#[cfg(windows)]
+fn executable_names(name: &str) -> Vec<String> {
+    let extensions = env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+    std::iter::once(name.to_string())
+        .chain(extensions.split(';').map(|ext| format!("{name}{ext}")))
+        .collect()
+}
+
+#[cfg(not(windows))]
+fn executable_names(name: &str) -> Vec<String> {
+    vec![name.to_string()]
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// First non-empty line of `program --version`, on stdout or, for tools
+/// like older `java`, stderr.
+fn tool_version(program: &Path) -> Option<String> {
+    let mut child = Command::new(program)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+    let started = Instant::now();
+    while child.try_wait().ok()?.is_none() {
+        if started.elapsed() > VERSION_TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            return None;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    let mut output = String::new();
+    child.stdout.take()?.read_to_string(&mut output).ok()?;
+    if output.trim().is_empty() {
+        child.stderr.take()?.read_to_string(&mut output).ok()?;
+    }
+    output
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reveal_refuses_a_missing_path_without_spawning() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.txt").to_string_lossy().to_string();
+        let err = reveal_in_file_manager(missing.clone()).unwrap_err();
+        assert_eq!(err, format!("{missing} does not exist"));
+    }
+
+    #[test]
+    fn check_tools_finds_git_and_reports_a_missing_tool() {
+        let missing = "titan-no-such-tool-4f1c";
+        let statuses = check_tools(vec!["git".into(), missing.into()]);
+
+        let names: Vec<&str> = statuses.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["git", missing]);
+        let git = &statuses[0];
+        assert!(git.found && git.path.is_some());
+        let version = git.version.as_deref().unwrap();
+        assert!(version.starts_with("git version"), "{version}");
+
+        let tool = &statuses[1];
+        assert!(!tool.found);
+        assert_eq!(
+            (tool.version.as_deref(), tool.path.as_deref()),
+            (None, None)
+        );
+    }
+}
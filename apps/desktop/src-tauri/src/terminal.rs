@@ -0,0 +1,370 @@
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// Running terminals keyed by session id.
+#[derive(Default)]
+pub struct TerminalManager(Mutex<HashMap<String, PtySession>>);
+
+impl TerminalManager {
+    /// Kills every session's shell.
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+/// A shell running on its own pseudo-terminal. Dropping it kills the shell.
+pub struct PtySession {
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send>,
+}
+
+/// Payload for the `terminal:output` event.
+#[derive(Clone, Serialize)]
+pub struct TerminalOutput {
+    pub session_id: String,
+    pub data: String,
+}
+
+/// Payload for the `terminal:exit` event, sent once the shell has gone.
+#[derive(Clone, Serialize)]
+pub struct TerminalExit {
+    pub session_id: String,
+}
+
+/// Result of `open_terminal_in`.
+#[derive(Serialize)]
+pub struct OpenedTerminal {
+    pub session_id: String,
+    /// Where the shell actually started.
+    pub cwd: String,
+    /// The requested directory didn't exist, so the shell started in the
+    /// home directory instead.
+    pub fell_back_to_home: bool,
+}
+
+fn default_shell() -> String {
+    if cfg!(windows) {
+        "cmd.exe".to_string()
+    } else {
+        std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string())
+    }
+}
+
+/// Arguments that start `shell` as a login shell, so it reads the user's
+/// profile the way a terminal emulator's would. Windows shells have no such
+/// mode and get none.
+fn login_args(shell: &str) -> &'static [&'static str] {
+    let name = Path::new(shell)
+        .file_stem()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if cfg!(windows) || matches!(name.as_str(), "cmd" | "powershell" | "pwsh") {
+        &[]
+    } else {
+        &["-l"]
+    }
+}
+
+/// Takes the longest valid UTF-8 prefix out of `pending`, leaving a
+/// multi-byte character split across reads for the next one. Bytes that can
+/// never be valid come out as replacement characters.
+fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let valid = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => pending.len(),
+    };
+    let text = String::from_utf8_lossy(&pending[..valid]).into_owned();
+    pending.drain(..valid);
+    text
+}
+
+impl PtySession {
+    /// Starts `shell` (or the user's default) in `cwd`, as a login shell
+    /// with `login`. `on_output` is called from a background thread with
+    /// everything the shell prints, and `on_exit` once after its last output.
+    pub fn spawn(
+        cwd: &str,
+        shell: Option<String>,
+        login: bool,
+        mut on_output: impl FnMut(String) + Send + 'static,
+        on_exit: impl FnOnce() + Send + 'static,
+    ) -> Result<Self, String> {
+        let pair = native_pty_system()
+            .openpty(PtySize {
+                rows: 24,
+                cols: 80,
+                pixel_width: 0,
+                pixel_height: 0,
+            })
+            .map_err(|e| e.to_string())?;
+        let shell = shell.unwrap_or_else(default_shell);
+        let mut command = CommandBuilder::new(&shell);
+        if login {
+            command.args(login_args(&shell));
+        }
+        command.cwd(cwd);
+        command.env("TERM", "xterm-256color");
+        let child = pair
+            .slave
+            .spawn_command(command)
+            .map_err(|e| format!("Cannot start shell in {cwd}: {e}"))?;
+        // Only the child should hold the slave, so the reader sees EOF when
+        // the shell exits
+        drop(pair.slave);
+
+        let mut reader = pair.master.try_clone_reader().map_err(|e| e.to_string())?;
+        let writer = pair.master.take_writer().map_err(|e| e.to_string())?;
+        thread::spawn(move || {
+            let mut buf = [0u8; 8192];
+            let mut pending = Vec::new();
+            while let Ok(n @ 1..) = reader.read(&mut buf) {
+                pending.extend_from_slice(&buf[..n]);
+                let text = take_utf8(&mut pending);
+                if !text.is_empty() {
+                    on_output(text);
+                }
+            }
+            on_exit();
+        });
+
+        Ok(PtySession {
+            master: pair.master,
+            writer,
+            child,
+        })
+    }
+
+    pub fn write(&mut self, data: &str) -> Result<(), String> {
+        self.writer
+            .write_all(data.as_bytes())
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn resize(&self, cols: u16, rows: u16) -> Result<(), String> {
+        self.master
+            .resize(PtySize {
+                rows,
+                cols,
+                pixel_width: 0,
+                pixel_height: 0,
+            })
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl Drop for PtySession {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Starts a terminal in `cwd` and returns its session id. Output arrives as
+/// `terminal:output` events and the end of the session as `terminal:exit`.
+#[tauri::command]
+pub fn spawn_terminal(
+    app: AppHandle,
+    state: State<'_, TerminalManager>,
+    cwd: String,
+    shell: Option<String>,
+) -> Result<String, String> {
+    start_terminal(app, state, cwd, shell, false)
+}
+
+fn start_terminal(
+    app: AppHandle,
+    state: State<'_, TerminalManager>,
+    cwd: String,
+    shell: Option<String>,
+    login: bool,
+) -> Result<String, String> {
+    let session_id = uuid::Uuid::new_v4().to_string();
+    let output_app = app.clone();
+    let output_id = session_id.clone();
+    let exit_id = session_id.clone();
+    // Held until the session is in the map, so a shell that exits straight
+    // away can't run the exit handler's remove before the insert
+    let mut sessions = state.0.lock().unwrap();
+    let session = PtySession::spawn(
+        &cwd,
+        shell,
+        login,
+        move |data| {
+            let _ = output_app.emit(
+                "terminal:output",
+                TerminalOutput {
+                    session_id: output_id.clone(),
+                    data,
+                },
+            );
+        },
+        move || {
+            // The shell may have exited on its own, e.g. after `exit`
+            app.state::<TerminalManager>()
+                .0
+                .lock()
+                .unwrap()
+                .remove(&exit_id);
+            let _ = app.emit(
+                "terminal:exit",
+                TerminalExit {
+                    session_id: exit_id,
+                },
+            );
+        },
+    )?;
+    sessions.insert(session_id.clone(), session);
+    Ok(session_id)
+}
+
+/// Where `open_terminal_in` starts: `dir` itself if it's an existing
+/// directory, otherwise `home`.
+fn terminal_dir(dir: &str, home: Option<PathBuf>) -> Result<(String, bool), String> {
+    if Path::new(dir).is_dir() {
+        return Ok((dir.to_string(), false));
+    }
+    match home {
+        Some(home) if home.is_dir() => Ok((home.to_string_lossy().to_string(), true)),
+        _ => Err(format!(
+            "{dir} is not a directory and there is no home directory"
+        )),
+    }
+}
+
+/// Starts the user's default shell in `dir` as a login shell, e.g. from a
+/// folder's context menu. A `dir` that has gone missing opens the home directory instead,
+/// which the result says so the UI can mention it.
+#[tauri::command]
+pub fn open_terminal_in(
+    app: AppHandle,
+    state: State<'_, TerminalManager>,
+    dir: String,
+) -> Result<OpenedTerminal, String> {
+    let (cwd, fell_back_to_home) = terminal_dir(&dir, app.path().home_dir().ok())?;
+    let session_id = start_terminal(app, state, cwd.clone(), None, true)?;
+    Ok(OpenedTerminal {
+        session_id,
+        cwd,
+        fell_back_to_home,
+    })
+}
+
+#[tauri::command]
+pub fn write_terminal(
+    state: State<'_, TerminalManager>,
+    session_id: String,
+    data: String,
+) -> Result<(), String> {
+    match state.0.lock().unwrap().get_mut(&session_id) {
+        Some(session) => session.write(&data),
+        None => Err(format!("No terminal session {session_id}")),
+    }
+}
+
+#[tauri::command]
+pub fn resize_terminal(
+    state: State<'_, TerminalManager>,
+    session_id: String,
+    cols: u16,
+    rows: u16,
+) -> Result<(), String> {
+    match state.0.lock().unwrap().get(&session_id) {
+        Some(session) => session.resize(cols, rows),
+        None => Err(format!("No terminal session {session_id}")),
+    }
+}
+
+/// Kills a session's shell. Unknown ids are ignored.
+#[tauri::command]
+pub fn kill_terminal(state: State<'_, TerminalManager>, session_id: String) {
+    let session = state.0.lock().unwrap().remove(&session_id);
+    drop(session);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn shell_echoes_back_what_is_written() {
+        use std::sync::mpsc;
+        use std::time::{Duration, Instant};
+
+        let (output, received) = mpsc::channel();
+        let (exited, exit) = mpsc::channel();
+        let mut session = PtySession::spawn(
+            "/",
+            Some("/bin/sh".into()),
+            false,
+            move |data| {
+                let _ = output.send(data);
+            },
+            move || {
+                let _ = exited.send(());
+            },
+        )
+        .unwrap();
+
+        // The arithmetic only shows up evaluated in the shell's output, not
+        // in the terminal's echo of the command line
+        session.write("echo round-$((20 + 22))-trip\n").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut seen = String::new();
+        while !seen.contains("round-42-trip") {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match received.recv_timeout(left) {
+                Ok(data) => seen.push_str(&data),
+                Err(_) => panic!("no echo, got {seen:?}"),
+            }
+        }
+
+        session.write("exit\n").unwrap();
+        exit.recv_timeout(Duration::from_secs(10)).unwrap();
+    }
+
+    #[test]
+    fn login_shells_get_dash_l_except_on_windows() {
+        assert!(login_args("cmd.exe").is_empty());
+        assert!(login_args("pwsh").is_empty());
+        assert!(login_args("powershell.exe").is_empty());
+        if cfg!(unix) {
+            assert_eq!(login_args("/bin/zsh"), ["-l"]);
+            assert_eq!(login_args("/usr/local/bin/fish"), ["-l"]);
+        }
+    }
+
+    #[test]
+    fn terminal_dir_uses_an_existing_directory() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().to_string_lossy().to_string();
+        assert_eq!(terminal_dir(&path, None).unwrap(), (path, false));
+    }
+
+    #[test]
+    fn terminal_dir_falls_back_to_home_for_a_missing_or_file_path() {
+        let home = tempfile::TempDir::new().unwrap();
+        let home_path = home.path().to_string_lossy().to_string();
+        let file = home.path().join("notes.txt");
+        std::fs::write(&file, "x").unwrap();
+
+        for dir in [home.path().join("gone"), file] {
+            let dir = dir.to_string_lossy().to_string();
+            let opened = terminal_dir(&dir, Some(home.path().to_path_buf())).unwrap();
+            assert_eq!(opened, (home_path.clone(), true));
+        }
+
+        let missing = home.path().join("gone").to_string_lossy().to_string();
+        assert!(terminal_dir(&missing, None).is_err());
+        assert!(terminal_dir(&missing, Some(home.path().join("no-home"))).is_err());
+    }
+}
@@ -0,0 +1,354 @@
+use crate::scan::{ScanCache, SKIP_DIRS};
+use notify_debouncer_full::notify::event::{EventKind, ModifyKind, RenameMode};
+use notify_debouncer_full::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, RecommendedCache};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::{self, File, Metadata};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// How long a path has to stay quiet before its change is reported. Bursts
+/// like a `git checkout` collapse into one event per kind.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Active watchers keyed by the root path the frontend asked for.
+#[derive(Default)]
+pub struct WatcherState(Mutex<HashMap<String, Debouncer<RecommendedWatcher, RecommendedCache>>>);
+
+impl WatcherState {
+    /// Drops every watcher, stopping its background thread.
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+/// Payload for the `fs:created`, `fs:removed` and `fs:modified` events.
+#[derive(Clone, Serialize)]
+pub struct FsChange {
+    pub root: String,
+    pub paths: Vec<String>,
+}
+
+/// True if any component below `root` is a directory the scan would skip,
+/// so churn in `target/` or `node_modules/` never reaches the UI.
+fn in_skipped_dir(root: &Path, path: &Path) -> bool {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return false;
+    };
+    relative
+        .components()
+        .any(|c| SKIP_DIRS.contains(&c.as_os_str().to_string_lossy().as_ref()))
+}
+
+fn emit_changes(app: &AppHandle, root: &str, result: DebounceEventResult) {
+    let Ok(events) = result else {
+        return;
+    };
+
+    let root_path = Path::new(root);
+    let mut created: Vec<PathBuf> = Vec::new();
+    let mut removed: Vec<PathBuf> = Vec::new();
+    let mut modified: Vec<PathBuf> = Vec::new();
+
+    for event in events {
+        match event.kind {
+            EventKind::Create(_) => created.extend(event.paths.iter().cloned()),
+            EventKind::Remove(_) => removed.extend(event.paths.iter().cloned()),
+            // A stitched rename carries `[from, to]`
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                removed.extend(event.paths.first().cloned());
+                created.extend(event.paths.get(1).cloned());
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                removed.extend(event.paths.iter().cloned())
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                created.extend(event.paths.iter().cloned())
+            }
+            EventKind::Modify(_) => modified.extend(event.paths.iter().cloned()),
+            _ => {}
+        }
+    }
+
+    for (name, paths) in [
+        ("fs:created", created),
+        ("fs:removed", removed),
+        ("fs:modified", modified),
+    ] {
+        let mut paths: Vec<String> = paths
+            .iter()
+            .filter(|p| !in_skipped_dir(root_path, p))
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        paths.sort();
+        paths.dedup();
+        if paths.is_empty() {
+            continue;
+        }
+        app.state::<ScanCache>().invalidate(root);
+
+        let _ = app.emit(
+            name,
+            FsChange {
+                root: root.to_string(),
+                paths,
+            },
+        );
+    }
+}
+
+/// Starts watching `path` recursively. Watching an already-watched path is a
+/// no-op.
+#[tauri::command]
+pub fn watch_directory(
+    app: AppHandle,
+    state: State<'_, WatcherState>,
+    path: String,
+) -> Result<(), String> {
+    let mut watchers = state.0.lock().unwrap();
+    if watchers.contains_key(&path) {
+        return Ok(());
+    }
+
+    let root = path.clone();
+    let mut debouncer = new_debouncer(DEBOUNCE, None, move |result| {
+        emit_changes(&app, &root, result)
+    })
+    .map_err(|e| e.to_string())?;
+    debouncer
+        .watch(&path, RecursiveMode::Recursive)
+        .map_err(|e| format!("Cannot watch {path}: {e}"))?;
+
+    watchers.insert(path, debouncer);
+    Ok(())
+}
+
+/// Stops watching `path`. Unknown paths are ignored.
+#[tauri::command]
+pub fn unwatch_directory(state: State<'_, WatcherState>, path: String) {
+    state.0.lock().unwrap().remove(&path);
+}
+
+/// Active `tail_file` watchers keyed by the path the frontend asked for.
+#[derive(Default)]
+pub struct TailState(Mutex<HashMap<String, Debouncer<RecommendedWatcher, RecommendedCache>>>);
+
+impl TailState {
+    /// Drops every tail, stopping its background thread.
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+/// Tells a file apart from one put in its place, e.g. by log rotation.
+#[cfg(unix)]
+fn file_identity(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some(metadata.ino())
+}
+
+/// Without inodes, rotation is only noticed when the file shrinks.
+#[cfg(not(unix))]
+fn file_identity(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+/// How far `tail_file` has read a file.
+struct TailCursor {
+    path: PathBuf,
+    offset: u64,
+    identity: Option<u64>,
+    /// The start of a UTF-8 character cut off by the end of the last read.
+    partial: Vec<u8>,
+    channel: Channel<String>,
+}
+
+impl TailCursor {
+    /// Sends whatever was appended since the last read. A file that shrank
+    /// or was replaced is read again from its start.
+    fn send_new(&mut self) {
+        // Missing between rotation's rename and create; the create brings
+        // us back here
+        let Ok(mut file) = File::open(&self.path) else {
+            return;
+        };
+        let Ok(metadata) = file.metadata() else {
+            return;
+        };
+        let identity = file_identity(&metadata);
+        if identity != self.identity || metadata.len() < self.offset {
+            self.identity = identity;
+            self.offset = 0;
+            self.partial.clear();
+        }
+        if metadata.len() == self.offset {
+            return;
+        }
+
+        let mut appended = Vec::new();
+        if file.seek(SeekFrom::Start(self.offset)).is_err()
+            || file.read_to_end(&mut appended).is_err()
+        {
+            return;
+        }
+        self.offset += appended.len() as u64;
+        let mut bytes = std::mem::take(&mut self.partial);
+        bytes.extend(appended);
+        let complete = match std::str::from_utf8(&bytes) {
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            _ => bytes.len(),
+        };
+        self.partial = bytes.split_off(complete);
+        if !bytes.is_empty() {
+            let _ = self
+                .channel
+                .send(String::from_utf8_lossy(&bytes).into_owned());
+        }
+    }
+}
+
+/// Sends the last `from_end_bytes` of the file at `path` over `channel`,
+/// then each chunk appended to it until `untail_file`. When the file is
+/// truncated or replaced, as log rotation does, the new contents are sent
+/// from the start. Tailing a path again replaces its channel.
+#[tauri::command]
+pub fn tail_file(
+    state: State<'_, TailState>,
+    path: String,
+    from_end_bytes: u64,
+    channel: Channel<String>,
+) -> Result<(), String> {
+    let debouncer = start_tail(&path, from_end_bytes, channel)?;
+    state.0.lock().unwrap().insert(path, debouncer);
+    Ok(())
+}
+
+fn start_tail(
+    path: &str,
+    from_end_bytes: u64,
+    channel: Channel<String>,
+) -> Result<Debouncer<RecommendedWatcher, RecommendedCache>, String> {
+    let file_path = fs::canonicalize(path).map_err(|e| format!("{path}: {e}"))?;
+    let mut file = File::open(&file_path).map_err(|e| format!("{path}: {e}"))?;
+    let metadata = file.metadata().map_err(|e| format!("{path}: {e}"))?;
+    if !metadata.is_file() {
+        return Err(format!("{path} is not a file"));
+    }
+
+    let mut start = metadata.len().saturating_sub(from_end_bytes);
+    if start > 0 {
+        // Skip to the next whole UTF-8 character
+        let mut head = [0; 3];
+        if file.seek(SeekFrom::Start(start)).is_ok() {
+            let read = file.read(&mut head).unwrap_or(0);
+            start += head[..read]
+                .iter()
+                .take_while(|&&b| b & 0xc0 == 0x80)
+                .count() as u64;
+        }
+    }
+    let mut cursor = TailCursor {
+        path: file_path.clone(),
+        offset: start,
+        identity: file_identity(&metadata),
+        partial: Vec::new(),
+        channel,
+    };
+    cursor.send_new();
+
+    // The directory is watched rather than the file so a replacement is
+    // picked up too
+    let Some(dir) = file_path.parent() else {
+        return Err(format!("{path} is not a file"));
+    };
+    let mut debouncer = new_debouncer(DEBOUNCE, None, move |result: DebounceEventResult| {
+        let touched = result.is_ok_and(|events| {
+            events
+                .iter()
+                .any(|event| event.paths.contains(&cursor.path))
+        });
+        if touched {
+            cursor.send_new();
+        }
+    })
+    .map_err(|e| e.to_string())?;
+    debouncer
+        .watch(dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Cannot watch {path}: {e}"))?;
+    Ok(debouncer)
+}
+
+/// Stops tailing `path`. Unknown paths are ignored.
+#[tauri::command]
+pub fn untail_file(state: State<'_, TailState>, path: String) {
+    let removed = state.0.lock().unwrap().remove(&path);
+    if let Some(debouncer) = removed {
+        // Waits out a last batch of events already being delivered, so
+        // nothing is sent after this returns
+        debouncer.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::Arc;
+    use std::time::Instant;
+    use tauri::ipc::InvokeResponseBody;
+
+    /// A channel that appends every string sent over it to the returned
+    /// buffer.
+    fn recording_channel() -> (Channel<String>, Arc<Mutex<String>>) {
+        let received = Arc::new(Mutex::new(String::new()));
+        let sink = received.clone();
+        let channel = Channel::new(move |body| {
+            if let InvokeResponseBody::Json(json) = body {
+                let text: String = serde_json::from_str(&json).unwrap();
+                sink.lock().unwrap().push_str(&text);
+            }
+            Ok(())
+        });
+        (channel, received)
+    }
+
+    fn wait_for(received: &Mutex<String>, want: &str) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while *received.lock().unwrap() != want {
+            assert!(
+                Instant::now() < deadline,
+                "got {:?}, want {want:?}",
+                received.lock().unwrap()
+            );
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    #[test]
+    fn tail_sends_the_end_then_each_append() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("app.log");
+        fs::write(&log, "old line\nlast line\n").unwrap();
+        let (channel, received) = recording_channel();
+
+        let debouncer = start_tail(&log.to_string_lossy(), 10, channel).unwrap();
+        assert_eq!(*received.lock().unwrap(), "last line\n");
+
+        let mut file = fs::OpenOptions::new().append(true).open(&log).unwrap();
+        file.write_all(b"one\ntwo\n").unwrap();
+        wait_for(&received, "last line\none\ntwo\n");
+        file.write_all(b"three\n").unwrap();
+        wait_for(&received, "last line\none\ntwo\nthree\n");
+        debouncer.stop();
+
+        let missing = dir.path().join("missing.log");
+        let (channel, _) = recording_channel();
+        assert!(start_tail(&missing.to_string_lossy(), 0, channel).is_err());
+    }
+}
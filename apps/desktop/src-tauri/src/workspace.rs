@@ -0,0 +1,115 @@
+use crate::files::write_atomic;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+/// Directory under the app config dir holding one file per project.
+const WORKSPACES_DIR: &str = "workspaces";
+
+/// What's stored for a project. The path is kept so a hash collision reads
+/// as no config rather than another project's.
+#[derive(Serialize, Deserialize)]
+struct StoredConfig {
+    project_path: String,
+    config: Value,
+}
+
+/// 64-bit FNV-1a. File names have to stay the same across builds, which
+/// std's `DefaultHasher` doesn't promise.
+fn path_hash(path: &str) -> u64 {
+    path.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn config_file(app: &AppHandle, project_path: &str) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| {
+            dir.join(WORKSPACES_DIR)
+                .join(format!("{:016x}.json", path_hash(project_path)))
+        })
+        .map_err(|e| e.to_string())
+}
+
+/// The stored config, or an empty object when there's none. As with the
+/// recent projects list, an unreadable or corrupt file counts as none.
+fn load(file: &Path, project_path: &str) -> Value {
+    fs::read(file)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<StoredConfig>(&bytes).ok())
+        .filter(|stored| stored.project_path == project_path)
+        .map_or_else(|| Value::Object(Default::default()), |stored| stored.config)
+}
+
+/// Writes with `write_atomic`, so a crash can't leave half a file behind.
+fn save(file: &Path, project_path: &str, config: Value) -> Result<(), String> {
+    let Some(dir) = file.parent() else {
+        return Err(format!("{} has no parent directory", file.display()));
+    };
+    fs::create_dir_all(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+    let stored = StoredConfig {
+        project_path: project_path.to_string(),
+        config,
+    };
+    let json = serde_json::to_vec_pretty(&stored).map_err(|e| e.to_string())?;
+    write_atomic(file, &json).map_err(|e| format!("{}: {e}", file.display()))
+}
+
+/// Settings remembered for the project at `project_path`, such as expanded
+/// tree nodes or the last opened file. An empty object until something is
+/// set.
+#[tauri::command]
+pub fn workspace_config_get(app: AppHandle, project_path: String) -> Result<Value, String> {
+    Ok(load(&config_file(&app, &project_path)?, &project_path))
+}
+
+/// Replaces the settings stored for `project_path` with `value`.
+#[tauri::command]
+pub fn workspace_config_set(
+    app: AppHandle,
+    project_path: String,
+    value: Value,
+) -> Result<(), String> {
+    save(&config_file(&app, &project_path)?, &project_path, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    #[test]
+    fn save_then_load_round_trips_a_project_config() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("workspaces/project.json");
+        let config = json!({ "expanded": ["src", "src/scan"], "lastFile": "src/main.rs" });
+
+        assert_eq!(load(&file, "/code/titan"), json!({}));
+        save(&file, "/code/titan", config.clone()).unwrap();
+        assert_eq!(load(&file, "/code/titan"), config);
+        // A different project hashing to the same file sees nothing
+        assert_eq!(load(&file, "/code/other"), json!({}));
+
+        save(&file, "/code/titan", json!({ "lastFile": null })).unwrap();
+        assert_eq!(load(&file, "/code/titan"), json!({ "lastFile": null }));
+        // Only the config itself is left in the directory
+        assert_eq!(fs::read_dir(file.parent().unwrap()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn a_truncated_config_loads_as_empty_and_can_be_saved_over() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("project.json");
+        save(&file, "/code/titan", json!({ "lastFile": "a.rs" })).unwrap();
+        let bytes = fs::read(&file).unwrap();
+        fs::write(&file, &bytes[..bytes.len() / 2]).unwrap();
+
+        assert_eq!(load(&file, "/code/titan"), json!({}));
+        save(&file, "/code/titan", json!({ "lastFile": "b.rs" })).unwrap();
+        assert_eq!(load(&file, "/code/titan"), json!({ "lastFile": "b.rs" }));
+    }
+}
@@ -7,7 +7,28 @@ import type { Channel } from "../types";
 interface DirEntry {
   name: string;
   path: string;
+  is_dir: boolean;
+  size: number;
+  modified: number | null;
+  kind: string;
+  is_symlink: boolean;
+  target: string | null;
+  ignored: boolean;
   children: DirEntry[];
+  truncated_count: number;
+  not_expanded: boolean;
+  binary: boolean | null;
+}
+
+interface ScanError {
+  path: string;
+  message: string;
+}
+
+interface ScanResult {
+  tree: DirEntry;
+  errors: ScanError[];
+  cancelled: boolean;
 }
 
 function buildChannelTree(entry: DirEntry): Channel {
@@ -108,7 +129,12 @@ export default function Sidebar() {
     });
     if (!selected) return;
 
-    const tree = await invoke<DirEntry>("scan_directory", { root: selected });
+    const { tree, errors } = await invoke<ScanResult>("scan_directory", {
+      root: selected,
+    });
+    if (errors.length > 0) {
+      console.warn(`Could not read ${errors.length} path(s) while scanning`, errors);
+    }
     const root = buildChannelTree(tree);
     dispatch({
       type: "SET_CHANNELS",